@@ -0,0 +1,29 @@
+use std::hint::black_box;
+use std::time::Instant;
+
+use json::Json;
+
+
+/// Time parsing Strings of many `\u` escapes, which are mostly in the BMP with
+/// a few surrogate pairs
+fn main()
+{
+	const RUNS: u32 = 20;
+
+	let bmp = "\\u4e2d\\u00e9\\u0041\\uffff".repeat(16);
+	let mixed = format!("{}\\ud83d\\ude00", "\\u4e2d\\u00e9\\u0041".repeat(4));
+	let bmp_data = format!("[{}]", vec![format!("\"{}\"", bmp); 10_000].join(","));
+	let mixed_data = format!("[{}]", vec![format!("\"{}\"", mixed); 40_000].join(","));
+
+	let start = Instant::now();
+	for _ in 0..RUNS {
+		black_box(Json::parse(black_box(bmp_data.as_bytes())));
+	}
+	println!("parse BMP escapes: {:?} per run", start.elapsed() / RUNS);
+
+	let start = Instant::now();
+	for _ in 0..RUNS {
+		black_box(Json::parse(black_box(mixed_data.as_bytes())));
+	}
+	println!("parse mixed escapes: {:?} per run", start.elapsed() / RUNS);
+}
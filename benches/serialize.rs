@@ -0,0 +1,55 @@
+use std::hint::black_box;
+use std::time::Instant;
+
+use json::Json;
+use json::Serializer;
+
+
+/// Time serializing a document of many numbers, which are the slowest leaves
+/// to format, and a document of many Objects and strings with and without
+/// reserving the capacity of the output
+fn main()
+{
+	const RUNS: u32 = 20;
+
+	let numbers = (0..200_000).map(|i| Json::Number(i as f64 * 1.25 - 1e5)).collect();
+	let value = Json::Array(numbers);
+
+	let start = Instant::now();
+	for _ in 0..RUNS {
+		black_box(black_box(&value).to_string());
+	}
+	println!("to_string: {:?} per run", start.elapsed() / RUNS);
+
+	let start = Instant::now();
+	for _ in 0..RUNS {
+		black_box(black_box(&value).serialized_len());
+	}
+	println!("serialized_len: {:?} per run", start.elapsed() / RUNS);
+
+	let records = (0..50_000).map(|i| {
+		let mut record = std::collections::BTreeMap::new();
+		record.insert("id".to_string(), Json::Number(i as f64));
+		record.insert("name".to_string(), Json::String(format!("record number {}", i)));
+		record.insert("tags".to_string(), Json::Array(vec![Json::String("a".repeat(20)), Json::Boolean(i % 2 == 0), Json::Null]));
+		Json::Object(record)
+	}).collect();
+	let value = Json::Array(records);
+	println!("records: {} bytes", value.serialized_len());
+
+	// Both copy the output once into a buffer which grows from empty, but
+	// only to_string reserves the output before serializing
+	let start = Instant::now();
+	for _ in 0..RUNS {
+		black_box(black_box(&value).to_string());
+	}
+	println!("to_string with reserving: {:?} per run", start.elapsed() / RUNS);
+
+	let start = Instant::now();
+	for _ in 0..RUNS {
+		let mut serializer = Serializer::new(Vec::new());
+		let _ = serializer.value(black_box(&value));
+		black_box(serializer.finish().ok());
+	}
+	println!("Serializer::value without reserving: {:?} per run", start.elapsed() / RUNS);
+}
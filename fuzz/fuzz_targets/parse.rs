@@ -0,0 +1,13 @@
+#![no_main]
+
+use json::Json;
+use libfuzzer_sys::fuzz_target;
+
+
+// Parsing must never panic or overflow the stack, and neither may serializing
+// or dropping the result
+fuzz_target!(|bytes: &[u8]| {
+	if let Ok(value) = Json::parse_detailed(bytes) {
+		let _ = value.to_string();
+	}
+});
@@ -1,109 +1,1611 @@
+#![allow(clippy::needless_return)]
+
+mod patch;
+mod pointer;
+mod recover;
+mod schema;
+mod serialize;
+mod source;
+mod span;
+mod stream;
+#[cfg(feature = "unicode-normalization")]
+mod normalize;
+#[cfg(feature = "serde_json")]
+mod serde_bridge;
+
+pub use patch::DiffChange;
+pub use patch::DiffEntry;
+pub use pointer::PatchError;
+pub use pointer::PointerOptions;
+pub use schema::SchemaError;
+pub use schema::SchemaErrorKind;
+pub use serialize::FormatError;
+pub use serialize::NonFinitePolicy;
+pub use serialize::NumberFormat;
+pub use serialize::SerializeError;
+pub use serialize::SerializeOptions;
+pub use serialize::Serializer;
+pub use source::SourceText;
+pub use span::Span;
+pub use stream::StreamingParser;
+
+use core::cmp::Ordering;
+use core::fmt;
+use core::ops::ControlFlow;
 use core::str::FromStr;
+use std::borrow::Cow;
 use std::collections::btree_map::Entry;
 use std::collections::BTreeMap;
 
+use crate::pointer::parse_index;
+
+
+/// A JSON value (JavaScript Object Notation). It's dropped without recursion,
+/// so the contents can't be moved out by a pattern like `Json::String(string)`
+/// on an owned value. Use `into_array`, `into_object`, or `into_string`
+/// instead.
+pub enum Json
+{
+	Array(Vec<Json>),
+	Boolean(bool),
+	Null,
+	Number(f64),
+	Object(BTreeMap<String, Json>),
+	String(String),
+}
+
+
+/// The type of a JSON value without its contents
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum JsonKind
+{
+	Array,
+	Boolean,
+	Null,
+	Number,
+	Object,
+	String,
+}
+
+
+/// Whether an Object member is missing, Null, or another value
+#[derive(Clone, Copy)]
+pub enum Presence<'a>
+{
+	Absent,
+	Null,
+	Value(&'a Json),
+}
+
+
+/// How `deep_merge_with` merges two Arrays
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ArrayMergePolicy
+{
+	/// The other Array replaces the Array
+	#[default]
+	Replace,
+	/// Elements at the same index merge, and the extra elements of a longer
+	/// other Array are appended
+	ByIndex,
+}
+
+
+/// Options which restrict or relax the JSON standard while parsing
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParseOptions<'a>
+{
+	/// Reject a root value which isn't an Array or Object, like RFC 4627
+	pub require_top_level_container: bool,
+	/// Accept control characters like tab and newline inside strings as they
+	/// are, instead of only as escape sequences
+	pub allow_raw_control_chars: bool,
+	/// Accept strings in single quotes like JSON5, where `\'` is an escape
+	/// sequence and `"` isn't escaped
+	pub allow_single_quotes: bool,
+	/// Accept Object keys without quotes like JSON5, if they're identifiers,
+	/// including `true`, `false`, and `null`
+	pub allow_unquoted_keys: bool,
+	/// Accept numbers like JSON5 in hexadecimal, with a leading `+`, or with a
+	/// leading or trailing decimal point
+	pub allow_json5_numbers: bool,
+	/// Accept integers with leading zeros like `007` as decimal numbers
+	pub allow_leading_zeros: bool,
+	/// Accept a comma after the last element or member like JSON5, like
+	/// `[1, 2,]`
+	pub allow_trailing_commas: bool,
+	/// Skip `//` line comments and `/* */` block comments like whitespace
+	pub allow_comments: bool,
+	/// Skip the UTF-8 byte order mark at the start of the data
+	pub allow_bom: bool,
+	/// Accept `NaN`, `Infinity`, and `-Infinity` as numbers like JSON5
+	pub allow_non_finite: bool,
+	/// Accept empty data or only whitespace as Null
+	pub empty_as_null: bool,
+	/// Parse `-0` and other negative zeros as `0`, so the sign doesn't
+	/// propagate
+	pub normalize_negative_zero: bool,
+	/// Reject data when the estimated heap bytes of the tokens and the value
+	/// would be more than this, which is a cap independent of the data length
+	pub max_total_bytes: Option<usize>,
+	/// Reject Object keys with more bytes than this after the escape sequences
+	pub max_key_len: Option<usize>,
+	/// Reject data with more tokens than this, like brackets, commas, and
+	/// scalars, which bounds the work for any structure
+	pub max_tokens: Option<usize>,
+	/// Skip these bytes like whitespace in addition to tab, newline, carriage
+	/// return, and space, like form feed from lenient producers
+	pub extra_whitespace: &'a [u8],
+	/// Reject numbers with an absolute value greater than this, and `NaN`
+	pub max_number_abs: Option<f64>,
+	/// Keep the source text of each Number, like `1e2` or `1.0`, in the
+	/// SourceText from `Json::parse_with_source_text`
+	pub keep_number_text: bool,
+	/// Keep the source text of each String and Object key, like `"\u0041"`,
+	/// in the SourceText from `Json::parse_with_source_text`
+	pub keep_string_text: bool,
+}
+
+
+impl<'a> ParseOptions<'a>
+{
+	/// Accept exactly RFC 8259 JSON, which is the same as the default
+	pub fn strict() -> ParseOptions<'a>
+	{
+		return ParseOptions::default();
+	}
+
+	/// Accept the common relaxations of producers which aren't strict, which
+	/// are trailing commas, comments, a byte order mark, and non-finite
+	/// numbers, but not the other syntax of JSON5
+	pub fn lenient() -> ParseOptions<'a>
+	{
+		return ParseOptions {
+			allow_trailing_commas: true,
+			allow_comments: true,
+			allow_bom: true,
+			allow_non_finite: true,
+			..ParseOptions::default()
+		};
+	}
+
+	/// Accept the relaxations of JSON5, which are single quotes, unquoted
+	/// keys, JSON5 numbers, trailing commas, comments, and non-finite numbers,
+	/// but not line continuations in strings or Unicode whitespace
+	pub fn json5() -> ParseOptions<'a>
+	{
+		return ParseOptions {
+			allow_single_quotes: true,
+			allow_unquoted_keys: true,
+			allow_json5_numbers: true,
+			allow_trailing_commas: true,
+			allow_comments: true,
+			allow_non_finite: true,
+			..ParseOptions::default()
+		};
+	}
+}
+
+
+/// The reason parsing failed and the byte offset where it was found
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ParseError
+{
+	pub kind: ParseErrorKind,
+	pub offset: usize,
+}
+
+
+/// The reason parsing failed
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ParseErrorKind
+{
+	/// A string has a raw control character like NUL or tab, which the options
+	/// don't allow, with the byte
+	ControlCharacter(u8),
+	/// An object has the same key more than once
+	DuplicateKey,
+	/// A `\u` escape sequence is a high surrogate without a low surrogate after
+	/// it, or a low surrogate alone
+	InvalidSurrogate,
+	/// The bytes aren't any JSON token
+	InvalidToken,
+	/// A string has bytes which aren't valid UTF-8
+	InvalidUtf8,
+	/// An Object key is longer than the options allow
+	KeyTooLong,
+	/// The estimated heap bytes are more than the options allow
+	MemoryLimit,
+	/// A number's absolute value is greater than the options allow
+	NumberTooLarge,
+	/// The reader failed before the data ended
+	Read,
+	/// The data has more tokens than the options allow
+	TooManyTokens,
+	/// The root value isn't an Array or Object, but the options require it
+	TopLevelScalar,
+	/// There's more data after the complete value
+	TrailingData,
+	/// The data ended before the value was complete
+	UnexpectedEnd,
+	/// The token isn't allowed in this position
+	UnexpectedToken,
+}
+
+
+enum Token
+{
+	ArrayBegin,
+	ArrayEnd,
+	Boolean(bool),
+	Colon,
+	Comma,
+	Identifier(String),
+	Null,
+	Number(f64),
+	ObjectBegin,
+	ObjectEnd,
+	String(String),
+}
+
+
+impl Json
+{
+	// TODO redo with only a few allocations, which can be reused
+	// TODO add tests like from https://github.com/nst/JSONTestSuite
+	/// Parse a JSON value in linear time if the data is valid JSON
+	///
+	/// Parsing never panics on any input, and it never recurses, so deeply
+	/// nested values can't overflow the stack. Dropping and serializing the
+	/// value don't recurse either.
+	pub fn parse(bytes: &[u8]) -> Option<Json>
+	{
+		return Json::parse_detailed(bytes).ok();
+	}
+
+	/// Parse a JSON value in linear time, otherwise get the reason and the
+	/// byte offset where it was found
+	pub fn parse_detailed(bytes: &[u8]) -> Result<Json, ParseError>
+	{
+		return Json::parse_with(bytes, &ParseOptions::default());
+	}
+
+	/// Parse a JSON value in linear time with the options, otherwise get the
+	/// reason and the byte offset where it was found
+	pub fn parse_with(bytes: &[u8], options: &ParseOptions) -> Result<Json, ParseError>
+	{
+		let mut tokens = Vec::<(usize, Token)>::new();
+		let tokenize_result = tokenize(bytes, &mut tokens, options);
+		if options.empty_as_null && tokenize_result.is_ok() && tokens.is_empty() {
+			return Ok(Json::Null);
+		}
+		let parse_result = parse(&mut tokens, bytes.len(), options);
+		return earliest_error(tokenize_result, parse_result);
+	}
+
+	/// Parse a JSON value into the root, which replaces the old value. If the
+	/// root and the new value are both an Array, Object, or String, then the
+	/// allocation of the root is reused. The root isn't changed if parsing
+	/// fails.
+	pub fn parse_into(bytes: &[u8], root: &mut Json) -> Result<(), ParseError>
+	{
+		let mut value = Json::parse_detailed(bytes)?;
+		match (&mut *root, &mut value) {
+			(Json::Array(old), Json::Array(new)) => {
+				old.clear();
+				old.append(new);
+			},
+			(Json::Object(old), Json::Object(new)) => {
+				old.clear();
+				old.append(new);
+			},
+			(Json::String(old), Json::String(new)) => {
+				old.clear();
+				old.push_str(new);
+			},
+			_ => *root = value,
+		}
+		return Ok(());
+	}
+
+	/// Parse data which is only a JSON number, with optional whitespace around
+	/// it, otherwise `None`
+	pub fn parse_number(bytes: &[u8]) -> Option<f64>
+	{
+		let bytes = trim_whitespace(bytes);
+		return match peek_number(bytes, &ParseOptions::default()) {
+			(len, number) if len > 0 && len == bytes.len() => Some(number),
+			_ => None,
+		};
+	}
+
+	/// Parse data which is only a JSON string in double quotes, with optional
+	/// whitespace around it, and get its content, otherwise `None`
+	pub fn parse_string(bytes: &[u8]) -> Option<String>
+	{
+		let bytes = trim_whitespace(bytes);
+		if bytes.first() != Some(&b'"') {
+			return None;
+		}
+		return match peek_string(bytes, &ParseOptions::default()) {
+			Ok((len, string)) if len == bytes.len() => Some(string),
+			_ => None,
+		};
+	}
+
+	/// Check if the value is an Array
+	pub fn is_array(&self) -> bool
+	{
+		return matches!(self, Json::Array(_));
+	}
+
+	/// Check if the value is a Boolean
+	pub fn is_boolean(&self) -> bool
+	{
+		return matches!(self, Json::Boolean(_));
+	}
+
+	/// Check if the value is Null
+	pub fn is_null(&self) -> bool
+	{
+		return matches!(self, Json::Null);
+	}
+
+	/// Check if the value is a Number
+	pub fn is_number(&self) -> bool
+	{
+		return matches!(self, Json::Number(_));
+	}
+
+	/// Check if the value is an Object
+	pub fn is_object(&self) -> bool
+	{
+		return matches!(self, Json::Object(_));
+	}
+
+	/// Check if the value is a String
+	pub fn is_string(&self) -> bool
+	{
+		return matches!(self, Json::String(_));
+	}
+
+	/// Check if the value is a scalar, or an Array or Object which only has
+	/// scalars
+	pub fn is_flat(&self) -> bool
+	{
+		let is_scalar = |value: &Json| !value.is_array() && !value.is_object();
+		return match self {
+			Json::Array(array) => array.iter().all(is_scalar),
+			Json::Object(object) => object.values().all(is_scalar),
+			_ => true,
+		};
+	}
+
+	/// Get the type of the value
+	pub fn kind(&self) -> JsonKind
+	{
+		return match self {
+			Json::Array(_) => JsonKind::Array,
+			Json::Boolean(_) => JsonKind::Boolean,
+			Json::Null => JsonKind::Null,
+			Json::Number(_) => JsonKind::Number,
+			Json::Object(_) => JsonKind::Object,
+			Json::String(_) => JsonKind::String,
+		};
+	}
+
+	/// Get the lowercase name of the type of the value, like `"array"`
+	pub fn type_name(&self) -> &'static str
+	{
+		return match self {
+			Json::Array(_) => "array",
+			Json::Boolean(_) => "boolean",
+			Json::Null => "null",
+			Json::Number(_) => "number",
+			Json::Object(_) => "object",
+			Json::String(_) => "string",
+		};
+	}
+
+	/// Check if every element of the Array has the same type, including an
+	/// empty Array, otherwise `None`
+	pub fn is_homogeneous_array(&self) -> Option<bool>
+	{
+		return match self {
+			Json::Array(array) => Some(array.windows(2).all(|pair| pair[0].kind() == pair[1].kind())),
+			_ => None,
+		};
+	}
+
+	/// Check if the value is truthy like JavaScript, where `false`, `null`,
+	/// `0`, `-0`, NaN, and `""` are falsy, but every Array and Object is truthy
+	pub fn is_truthy(&self) -> bool
+	{
+		return match self {
+			Json::Array(_) | Json::Object(_) => true,
+			Json::Boolean(boolean) => *boolean,
+			Json::Null => false,
+			Json::Number(number) => *number != 0.0 && !number.is_nan(),
+			Json::String(string) => !string.is_empty(),
+		};
+	}
+
+	/// Check if the value is truthy like `is_truthy`, except empty Arrays and
+	/// Objects are falsy like Python
+	pub fn is_truthy_nonempty(&self) -> bool
+	{
+		return match self {
+			Json::Array(array) => !array.is_empty(),
+			Json::Object(object) => !object.is_empty(),
+			scalar => scalar.is_truthy(),
+		};
+	}
+
+	/// Get whether the member of the Object is missing, Null, or another
+	/// value, where it's missing if the value isn't an Object
+	pub fn get_presence(&self, key: &str) -> Presence<'_>
+	{
+		return match self {
+			Json::Object(object) => match object.get(key) {
+				Some(Json::Null) => Presence::Null,
+				Some(member) => Presence::Value(member),
+				None => Presence::Absent,
+			},
+			_ => Presence::Absent,
+		};
+	}
+
+	/// Get the member of the Object, inserting the default first if it's
+	/// missing, otherwise `None` if the value isn't an Object
+	pub fn get_or_insert_with(&mut self, key: impl Into<String>, default: impl FnOnce() -> Json) -> Option<&mut Json>
+	{
+		return match self {
+			Json::Object(object) => Some(object.entry(key.into()).or_insert_with(default)),
+			_ => None,
+		};
+	}
+
+	/// Get the value by descending through the Object member of each key,
+	/// otherwise `None`
+	pub fn deep_get<K: AsRef<str>>(&self, path: &[K]) -> Option<&Json>
+	{
+		let mut value = self;
+		for key in path {
+			value = match value {
+				Json::Object(object) => object.get(key.as_ref())?,
+				_ => return None,
+			};
+		}
+		return Some(value);
+	}
+
+	/// Get the mutable value by descending through the Object member of each
+	/// key, otherwise `None`
+	pub fn deep_get_mut<K: AsRef<str>>(&mut self, path: &[K]) -> Option<&mut Json>
+	{
+		let mut value = self;
+		for key in path {
+			value = match value {
+				Json::Object(object) => object.get_mut(key.as_ref())?,
+				_ => return None,
+			};
+		}
+		return Some(value);
+	}
+
+	/// Replace every String value, but not the Object keys, with the result of
+	/// the function
+	pub fn map_strings(&mut self, f: impl Fn(&str) -> String)
+	{
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			match value {
+				Json::Array(array) => stack.extend(array.iter_mut()),
+				Json::Object(object) => stack.extend(object.values_mut()),
+				Json::String(string) => *string = f(string),
+				_ => (),
+			}
+		}
+	}
+
+	/// Replace every Object key with the result of the function, where the
+	/// last key in the original order wins if they collide
+	pub fn map_keys(&mut self, f: impl Fn(&str) -> String)
+	{
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			match value {
+				Json::Array(array) => stack.extend(array.iter_mut()),
+				Json::Object(object) => {
+					for (key, member) in core::mem::take(object) {
+						object.insert(f(&key), member);
+					}
+					stack.extend(object.values_mut());
+				},
+				_ => (),
+			}
+		}
+	}
+
+	/// Append the elements of an Array to an Array, or insert the members of
+	/// an Object into an Object where the other members win, otherwise `false`
+	/// if the types don't match
+	pub fn extend(&mut self, mut other: Json) -> bool
+	{
+		match (self, &mut other) {
+			(Json::Array(array), Json::Array(other_array)) => array.append(other_array),
+			(Json::Object(object), Json::Object(other_object)) => object.append(other_object),
+			_ => return false,
+		}
+		return true;
+	}
+
+	/// Merge the other value into the value, where Objects merge their members
+	/// recursively, and otherwise the other value wins, including Arrays
+	pub fn deep_merge(&mut self, other: Json)
+	{
+		self.deep_merge_with(other, ArrayMergePolicy::Replace);
+	}
+
+	/// Merge the other value into the value, where Objects merge their members
+	/// recursively, Arrays merge by the policy, and otherwise the other value
+	/// wins
+	pub fn deep_merge_with(&mut self, other: Json, array_policy: ArrayMergePolicy)
+	{
+		let mut stack = vec![(self, other)];
+		while let Some((value, mut other)) = stack.pop() {
+			let merges = match (&*value, &other) {
+				(Json::Array(_), Json::Array(_)) => array_policy == ArrayMergePolicy::ByIndex,
+				(Json::Object(_), Json::Object(_)) => true,
+				_ => false,
+			};
+			if !merges {
+				*value = other;
+				continue;
+			}
+			match (value, &mut other) {
+				(Json::Array(array), Json::Array(other_array)) => {
+					let mut other_elements = core::mem::take(other_array);
+					let extra_elements = other_elements.split_off(array.len().min(other_elements.len()));
+					array.extend(extra_elements);
+					stack.extend(array.iter_mut().zip(other_elements));
+				},
+				(Json::Object(object), Json::Object(other_object)) => {
+					// Insert the new members, then merge the members of the same key
+					let mut conflicts = BTreeMap::<String, Json>::new();
+					for (key, other_member) in core::mem::take(other_object) {
+						match object.contains_key(&key) {
+							true => conflicts.insert(key, other_member),
+							false => object.insert(key, other_member),
+						};
+					}
+					for (key, member) in object.iter_mut() {
+						if let Some(other_member) = conflicts.remove(key) {
+							stack.push((member, other_member));
+						}
+					}
+				},
+				_ => (),
+			}
+		}
+	}
+
+	/// Deeply merge the documents from first to last like `deep_merge`, such as
+	/// layers of configuration, otherwise Null if there aren't any
+	pub fn merge_all(docs: impl IntoIterator<Item = Json>) -> Json
+	{
+		let mut docs = docs.into_iter();
+		let mut merged = match docs.next() {
+			Some(doc) => doc,
+			None => return Json::Null,
+		};
+		for doc in docs {
+			merged.deep_merge(doc);
+		}
+		return merged;
+	}
+
+	/// Add the members of the defaults which are missing, recursively for
+	/// Objects, without changing the members which exist, including Null
+	pub fn fill_defaults(&mut self, defaults: &Json)
+	{
+		let mut stack = vec![(self, defaults)];
+		while let Some(pair) = stack.pop() {
+			if let (Json::Object(object), Json::Object(default_object)) = pair {
+				for (key, default_member) in default_object {
+					if !object.contains_key(key) {
+						object.insert(key.clone(), default_member.clone());
+					}
+				}
+				for (key, member) in object.iter_mut() {
+					if let Some(default_member @ Json::Object(_)) = default_object.get(key) {
+						stack.push((member, default_member));
+					}
+				}
+			}
+		}
+	}
+
+	/// Get the String as it is, or the text of the other scalars like `12.5`,
+	/// `true`, or `null`, otherwise `None` for an Array or Object
+	pub fn as_str_lossy(&self) -> Option<Cow<'_, str>>
+	{
+		return match self {
+			Json::Array(_) | Json::Object(_) => None,
+			Json::String(string) => Some(Cow::Borrowed(string)),
+			scalar => Some(Cow::Owned(scalar.to_string())),
+		};
+	}
+
+	/// Get the keys of the Object in sorted order, otherwise `None`
+	pub fn keys(&self) -> Option<impl Iterator<Item = &str>>
+	{
+		return match self {
+			Json::Object(object) => Some(object.keys().map(String::as_str)),
+			_ => None,
+		};
+	}
+
+	/// Get the values of the Object in the order of their keys, otherwise
+	/// `None`
+	pub fn values(&self) -> Option<impl Iterator<Item = &Json>>
+	{
+		return match self {
+			Json::Object(object) => Some(object.values()),
+			_ => None,
+		};
+	}
+
+	/// Get the members of the Object sorted by the comparison, like by value
+	/// instead of by key, otherwise `None`
+	pub fn entries_sorted_by(&self, cmp: impl FnMut(&(&str, &Json), &(&str, &Json)) -> Ordering) -> Option<Vec<(&str, &Json)>>
+	{
+		let mut entries = match self {
+			Json::Object(object) => object.iter().map(|(key, member)| (key.as_str(), member)).collect::<Vec<_>>(),
+			_ => return None,
+		};
+		entries.sort_by(cmp);
+		return Some(entries);
+	}
+
+	/// Accumulate every Number in the value, like for a sum or maximum
+	pub fn fold_numbers<T>(&self, init: T, mut f: impl FnMut(T, f64) -> T) -> T
+	{
+		let mut accumulator = init;
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			match value {
+				Json::Array(array) => stack.extend(array.iter().rev()),
+				Json::Number(number) => accumulator = f(accumulator, *number),
+				Json::Object(object) => stack.extend(object.values().rev()),
+				_ => (),
+			}
+		}
+		return accumulator;
+	}
+
+	/// Replace every value deeper than the maximum depth with the placeholder,
+	/// like `"..."` for a preview, where the root is at depth 0
+	pub fn truncate_depth(&mut self, max_depth: usize, placeholder: Json)
+	{
+		let mut stack = vec![(self, 0)];
+		while let Some((value, depth)) = stack.pop() {
+			match (value, depth < max_depth) {
+				(Json::Array(array), true) => stack.extend(array.iter_mut().map(|element| (element, depth + 1))),
+				(Json::Object(object), true) => stack.extend(object.values_mut().map(|member| (member, depth + 1))),
+				(Json::Array(array), false) => array.fill(placeholder.clone()),
+				(Json::Object(object), false) => {
+					for member in object.values_mut() {
+						*member = placeholder.clone();
+					}
+				},
+				_ => (),
+			}
+		}
+	}
+
+	/// Replace every Array of exactly one element with the element
+	pub fn unwrap_singletons(&mut self)
+	{
+		self.unwrap_singletons_with(false);
+	}
+
+	/// Replace every Array of exactly one element with the element, and every
+	/// Object of exactly one member with the member if `objects` is `true`
+	pub fn unwrap_singletons_with(&mut self, objects: bool)
+	{
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			loop {
+				let inner = match value {
+					Json::Array(array) if array.len() == 1 => array.pop(),
+					Json::Object(object) if objects && object.len() == 1 => object.pop_first().map(|(_, member)| member),
+					_ => None,
+				};
+				match inner {
+					Some(inner) => *value = inner,
+					None => break,
+				}
+			}
+			match value {
+				Json::Array(array) => stack.extend(array.iter_mut()),
+				Json::Object(object) => stack.extend(object.values_mut()),
+				_ => (),
+			}
+		}
+	}
+
+	/// Get the members of the Object as an Array of `[key, value]` Arrays in
+	/// the order of the keys, like `Object.entries` in JavaScript, otherwise
+	/// `None`
+	pub fn entries_to_array(&self) -> Option<Json>
+	{
+		return match self {
+			Json::Object(object) => Some(object.iter()
+				.map(|(key, member)| Json::Array(vec![Json::String(key.clone()), member.clone()]))
+				.collect()),
+			_ => None,
+		};
+	}
+
+	/// Get the Object of an Array of `[key, value]` Arrays, like
+	/// `Object.fromEntries` in JavaScript, where the last entry wins if the
+	/// keys are the same, otherwise `None` if any entry isn't a String and a
+	/// value
+	pub fn from_entries_array(entries: Json) -> Option<Json>
+	{
+		let mut object = BTreeMap::<String, Json>::new();
+		for mut entry in entries.into_array()? {
+			match &mut entry {
+				Json::Array(pair) if pair.len() == 2 => {
+					let value = pair.pop()?;
+					let key = match &mut pair[0] {
+						Json::String(key) => core::mem::take(key),
+						_ => return None,
+					};
+					object.insert(key, value);
+				},
+				_ => return None,
+			}
+		}
+		return Some(Json::Object(object));
+	}
+
+	/// Build an Object from the keys and the values in the same order, like a
+	/// record from columns, otherwise fail if the lengths are different or a
+	/// key is duplicated
+	pub fn object_from_columns(keys: &[String], values: Vec<Json>) -> Result<Json, &'static str>
+	{
+		if keys.len() != values.len() {
+			return Err("different numbers of keys and values");
+		}
+		let mut object = BTreeMap::<String, Json>::new();
+		for (key, value) in keys.iter().zip(values) {
+			match object.entry(key.clone()) {
+				Entry::Occupied(_) => return Err("duplicate key"),
+				Entry::Vacant(entry) => entry.insert(value),
+			};
+		}
+		return Ok(Json::Object(object));
+	}
+
+	/// Flatten the nested Objects and Arrays into keys joined by the separator,
+	/// like `{"a": {"b": [1]}}` into `{"a.b.0": 1}`, where the scalars and
+	/// empty containers are the values. A root scalar or empty container has
+	/// the empty key.
+	pub fn to_dotted(&self, separator: &str) -> BTreeMap<String, Json>
+	{
+		let mut dotted = BTreeMap::<String, Json>::new();
+		let mut stack = vec![(self, None::<String>)];
+		while let Some((value, key)) = stack.pop() {
+			let join = |child_key: &str| match &key {
+				Some(key) => Some(format!("{}{}{}", key, separator, child_key)),
+				None => Some(child_key.to_string()),
+			};
+			match value {
+				Json::Array(array) if !array.is_empty() => {
+					for (i, element) in array.iter().enumerate() {
+						stack.push((element, join(&i.to_string())));
+					}
+				},
+				Json::Object(object) if !object.is_empty() => {
+					for (member_key, member) in object {
+						stack.push((member, join(member_key)));
+					}
+				},
+				_ => {
+					dotted.insert(key.unwrap_or_default(), value.clone());
+				},
+			}
+		}
+		return dotted;
+	}
+
+	/// Build the nested value from keys joined by the separator like
+	/// `to_dotted`, where Objects with exactly the keys `0` to `n - 1` become
+	/// Arrays, and a single empty key is the root. A value replaces a shorter
+	/// key which is a prefix of it, like `a.b` replaces `a`.
+	pub fn from_dotted(dotted: BTreeMap<String, Json>, separator: &str) -> Json
+	{
+		if dotted.len() == 1 && dotted.contains_key("") {
+			return dotted.into_values().next().unwrap_or(Json::Null);
+		}
+
+		let mut root = Json::Object(BTreeMap::new());
+		for (key, value) in dotted {
+			let tokens = key.split(separator).collect::<Vec<_>>();
+			let (last, parents) = match tokens.split_last() {
+				Some(split) => split,
+				None => continue,
+			};
+			let mut parent = &mut root;
+			for token in parents {
+				if !parent.is_object() {
+					*parent = Json::Object(BTreeMap::new());
+				}
+				parent = match parent {
+					Json::Object(object) => object.entry(token.to_string()).or_insert_with(|| Json::Object(BTreeMap::new())),
+					_ => unreachable!(),
+				};
+			}
+			if !parent.is_object() {
+				*parent = Json::Object(BTreeMap::new());
+			}
+			if let Json::Object(object) = parent {
+				object.insert(last.to_string(), value);
+			}
+		}
+
+		// Convert the Objects with the keys of indices to Arrays
+		let mut stack = vec![&mut root];
+		while let Some(value) = stack.pop() {
+			if let Json::Object(object) = value {
+				let len = object.len();
+				if len > 0 && object.keys().all(|key| parse_index(key).is_some_and(|index| index < len)) {
+					let mut elements = core::mem::take(object).into_iter()
+						.map(|(key, element)| (parse_index(&key).unwrap_or(0), element))
+						.collect::<Vec<_>>();
+					elements.sort_by_key(|(index, _)| *index);
+					*value = elements.into_iter().map(|(_, element)| element).collect();
+				}
+			}
+			match value {
+				Json::Array(array) => stack.extend(array.iter_mut()),
+				Json::Object(object) => stack.extend(object.values_mut()),
+				_ => (),
+			}
+		}
+		return root;
+	}
+
+	/// Check if every Number is finite, so the value can be serialized as
+	/// standard JSON without NaN or infinity
+	pub fn all_finite(&self) -> bool
+	{
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			match value {
+				Json::Array(array) => stack.extend(array),
+				Json::Number(number) if !number.is_finite() => return false,
+				Json::Object(object) => stack.extend(object.values()),
+				_ => (),
+			}
+		}
+		return true;
+	}
+
+	/// Replace every Number which is NaN or infinite with Null, so the value
+	/// can be serialized as standard JSON
+	pub fn nullify_non_finite(&mut self)
+	{
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			match value {
+				Json::Array(array) => stack.extend(array.iter_mut()),
+				Json::Number(number) if !number.is_finite() => *value = Json::Null,
+				Json::Object(object) => stack.extend(object.values_mut()),
+				_ => (),
+			}
+		}
+	}
+
+	/// Call the function with each element of the Array in order until it
+	/// breaks, otherwise do nothing if the value isn't an Array
+	pub fn for_each_element_mut(&mut self, mut f: impl FnMut(&mut Json) -> ControlFlow<()>)
+	{
+		if let Json::Array(array) = self {
+			for element in array {
+				if f(element).is_break() {
+					return;
+				}
+			}
+		}
+	}
+
+	/// Replace every value which matches the predicate with the replacement
+	/// for it, where the values inside a replaced value aren't checked
+	pub fn replace_where(&mut self, pred: impl Fn(&Json) -> bool, replacement: impl Fn(&Json) -> Json)
+	{
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			if pred(value) {
+				*value = replacement(value);
+				continue;
+			}
+			match value {
+				Json::Array(array) => stack.extend(array.iter_mut()),
+				Json::Object(object) => stack.extend(object.values_mut()),
+				_ => (),
+			}
+		}
+	}
+
+	/// Replace every String value which is a JSON number, like `"42"` or
+	/// `"1e3"`, with the Number, but not the Object keys
+	pub fn coerce_numeric_strings(&mut self)
+	{
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			match value {
+				Json::Array(array) => stack.extend(array.iter_mut()),
+				Json::Object(object) => stack.extend(object.values_mut()),
+				Json::String(string) => {
+					let (len, number) = peek_number(string.as_bytes(), &ParseOptions::default());
+					if len > 0 && len == string.len() {
+						*value = Json::Number(number);
+					}
+				},
+				_ => (),
+			}
+		}
+	}
+
+	/// Count each distinct element of the Array, sorted by the count from
+	/// most to least, where equal counts are in the order of first appearance,
+	/// otherwise `None`. Elements are distinct by their canonical
+	/// serialization, so `0` and `-0` are counted together, and so are NaNs.
+	pub fn value_counts(&self) -> Option<Vec<(Json, usize)>>
+	{
+		let array = match self {
+			Json::Array(array) => array,
+			_ => return None,
+		};
+		let options = SerializeOptions {
+			non_finite: NonFinitePolicy::AllowLiteral,
+			normalize_negative_zero: true,
+			..SerializeOptions::default()
+		};
+		// The index of each distinct element in the counts by its serialization
+		let mut indexes = BTreeMap::<String, usize>::new();
+		let mut counts = Vec::<(Json, usize)>::new();
+		for element in array {
+			let serialized = match element.to_string_with(&options) {
+				Ok(serialized) => serialized,
+				Err(_) => unreachable!(),
+			};
+			match indexes.entry(serialized) {
+				Entry::Occupied(entry) => counts[*entry.get()].1 += 1,
+				Entry::Vacant(entry) => {
+					entry.insert(counts.len());
+					counts.push((element.clone(), 1));
+				},
+			}
+		}
+		counts.sort_by_key(|&(_, count)| core::cmp::Reverse(count));
+		return Some(counts);
+	}
+
+	/// Get every String value which matches the predicate in document order,
+	/// but not the Object keys
+	pub fn find_strings(&self, pred: impl Fn(&str) -> bool) -> Vec<&str>
+	{
+		let mut strings = Vec::<&str>::new();
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			match value {
+				Json::Array(array) => stack.extend(array.iter().rev()),
+				Json::Object(object) => stack.extend(object.values().rev()),
+				Json::String(string) if pred(string) => strings.push(string),
+				_ => (),
+			}
+		}
+		return strings;
+	}
+
+	/// Remove every Object member which is Null, but keep the Null elements of
+	/// Arrays since their positions matter
+	pub fn strip_nulls(&mut self)
+	{
+		self.strip_nulls_with(false);
+	}
+
+	/// Remove every Object member which is Null, and the Null elements of
+	/// Arrays if `in_arrays` is `true`
+	pub fn strip_nulls_with(&mut self, in_arrays: bool)
+	{
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			match value {
+				Json::Array(array) => {
+					if in_arrays {
+						array.retain(|element| !element.is_null());
+					}
+					stack.extend(array.iter_mut());
+				},
+				Json::Object(object) => {
+					object.retain(|_, member| !member.is_null());
+					stack.extend(object.values_mut());
+				},
+				_ => (),
+			}
+		}
+	}
+
+	/// Count the values of each type in the whole value, including itself, by
+	/// the type name like `"array"`
+	pub fn type_histogram(&self) -> BTreeMap<&'static str, usize>
+	{
+		let mut histogram = BTreeMap::<&'static str, usize>::new();
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			*histogram.entry(value.type_name()).or_insert(0) += 1;
+			match value {
+				Json::Array(array) => stack.extend(array),
+				Json::Object(object) => stack.extend(object.values()),
+				_ => (),
+			}
+		}
+		return histogram;
+	}
+
+	/// Get the part of the value which is shared with the other value, like
+	/// for factoring out common configuration. Objects keep the members of the
+	/// same keys whose values are common, where two Objects are always common,
+	/// and other values are common only if they're equal. Members which aren't
+	/// common are absent, and the result is Null if the root isn't common.
+	pub fn common(&self, other: &Json) -> Json
+	{
+		let is_common = |value: &Json, other_value: &Json| match (value, other_value) {
+			(Json::Object(_), Json::Object(_)) => true,
+			(value, other_value) => value == other_value,
+		};
+		if !is_common(self, other) {
+			return Json::Null;
+		}
+
+		let mut common = self.clone();
+		let mut stack = vec![(&mut common, other)];
+		while let Some(pair) = stack.pop() {
+			if let (Json::Object(object), Json::Object(other_object)) = pair {
+				object.retain(|key, member| other_object.get(key).is_some_and(|other_member| is_common(member, other_member)));
+				for (key, member) in object.iter_mut() {
+					if let Some(other_member) = other_object.get(key) {
+						stack.push((member, other_member));
+					}
+				}
+			}
+		}
+		return common;
+	}
+
+	/// Check if the values are equal down to the maximum depth, where the
+	/// values deeper than it are always equal and the root is at depth 0
+	pub fn eq_to_depth(&self, other: &Json, max_depth: usize) -> bool
+	{
+		let mut stack = vec![(self, other, 0)];
+		while let Some((value, other_value, depth)) = stack.pop() {
+			if depth > max_depth {
+				continue;
+			}
+			match (value, other_value) {
+				(Json::Array(array), Json::Array(other_array)) => {
+					if array.len() != other_array.len() {
+						return false;
+					}
+					stack.extend(array.iter().zip(other_array).map(|(element, other_element)| (element, other_element, depth + 1)));
+				},
+				(Json::Object(object), Json::Object(other_object)) => {
+					if object.len() != other_object.len() {
+						return false;
+					}
+					for ((key, member), (other_key, other_member)) in object.iter().zip(other_object) {
+						if key != other_key {
+							return false;
+						}
+						stack.push((member, other_member, depth + 1));
+					}
+				},
+				(Json::Array(_) | Json::Object(_), _) => return false,
+				(value, other_value) if value != other_value => return false,
+				_ => (),
+			}
+		}
+		return true;
+	}
 
-/// A JSON value (JavaScript Object Notation)
-pub enum Json
-{
-	Array(Vec<Json>),
-	Boolean(bool),
-	Null,
-	Number(f64),
-	Object(BTreeMap<String, Json>),
-	String(String),
+	/// Check if the value is equal to any of the allowed values, like the
+	/// `enum` of a schema
+	pub fn is_one_of(&self, allowed: &[Json]) -> bool
+	{
+		return allowed.contains(self);
+	}
+
+	/// Clone the elements of the Array into an Array of those which match the
+	/// predicate and an Array of the rest, otherwise `None`
+	pub fn partition_array(&self, pred: impl Fn(&Json) -> bool) -> Option<(Json, Json)>
+	{
+		return match self {
+			Json::Array(array) => {
+				let (matching, rest) = array.iter().cloned().partition(|element| pred(element));
+				Some((Json::Array(matching), Json::Array(rest)))
+			},
+			_ => None,
+		};
+	}
+
+	/// Take the Array, otherwise `None`
+	pub fn into_array(mut self) -> Option<Vec<Json>>
+	{
+		return match &mut self {
+			Json::Array(array) => Some(core::mem::take(array)),
+			_ => None,
+		};
+	}
+
+	/// Get the Array as it is, otherwise wrap the value in an Array, like for
+	/// a member which may be one value or an Array of them
+	pub fn into_array_coerce(self) -> Json
+	{
+		return match self {
+			Json::Array(_) => self,
+			_ => Json::Array(vec![self]),
+		};
+	}
+
+	/// Take the Object, otherwise `None`
+	pub fn into_object(mut self) -> Option<BTreeMap<String, Json>>
+	{
+		return match &mut self {
+			Json::Object(object) => Some(core::mem::take(object)),
+			_ => None,
+		};
+	}
+
+	/// Take the String, otherwise `None`
+	pub fn into_string(mut self) -> Option<String>
+	{
+		return match &mut self {
+			Json::String(string) => Some(core::mem::take(string)),
+			_ => None,
+		};
+	}
+
+	/// Check if the value has the same shape as the template, ignoring the
+	/// scalar values. Objects need the same keys with the same shapes. Arrays
+	/// need every element to have the shape of the first template element,
+	/// unless the template Array is empty.
+	pub fn matches_shape(&self, template: &Json) -> bool
+	{
+		let mut stack = vec![(self, template)];
+		while let Some(pair) = stack.pop() {
+			match pair {
+				(Json::Array(array), Json::Array(template_array)) => {
+					if let Some(template_element) = template_array.first() {
+						stack.extend(array.iter().map(|element| (element, template_element)));
+					}
+				},
+				(Json::Object(object), Json::Object(template_object)) => {
+					if object.len() != template_object.len() {
+						return false;
+					}
+					for ((key, member), (template_key, template_member)) in object.iter().zip(template_object) {
+						if key != template_key {
+							return false;
+						}
+						stack.push((member, template_member));
+					}
+				},
+				(Json::Boolean(_), Json::Boolean(_)) => (),
+				(Json::Null, Json::Null) => (),
+				(Json::Number(_), Json::Number(_)) => (),
+				(Json::String(_), Json::String(_)) => (),
+				_ => return false,
+			}
+		}
+		return true;
+	}
+
+	/// Clone only the members in the template, like for a projection. Each
+	/// Object keeps the members with the keys of the template Object, where a
+	/// template member which is an Object or Array projects the member, and
+	/// other template members keep the whole member. Each element of an Array
+	/// is projected by the first element of the template Array.
+	pub fn project(&self, template: &Json) -> Json
+	{
+		let mut projection = self.clone();
+		let mut stack = vec![(&mut projection, template)];
+		while let Some(pair) = stack.pop() {
+			match pair {
+				(Json::Array(array), Json::Array(template_array)) => {
+					if let Some(template_element) = template_array.first() {
+						stack.extend(array.iter_mut().map(|element| (element, template_element)));
+					}
+				},
+				(Json::Object(object), Json::Object(template_object)) => {
+					object.retain(|key, _| template_object.contains_key(key));
+					for (key, member) in object.iter_mut() {
+						if let Some(template_member) = template_object.get(key) {
+							stack.push((member, template_member));
+						}
+					}
+				},
+				_ => (),
+			}
+		}
+		return projection;
+	}
+
+	/// Check if the value is contained in the other value, like for testing
+	/// that a response has at least the expected members. Each Object member
+	/// needs to be in the other Object as a subset, Arrays need the same length
+	/// with each element as a subset, and scalars need to be equal.
+	pub fn is_subset_of(&self, other: &Json) -> bool
+	{
+		let mut stack = vec![(self, other)];
+		while let Some(pair) = stack.pop() {
+			match pair {
+				(Json::Array(array), Json::Array(other_array)) => {
+					if array.len() != other_array.len() {
+						return false;
+					}
+					stack.extend(array.iter().zip(other_array));
+				},
+				(Json::Object(object), Json::Object(other_object)) => {
+					for (key, member) in object {
+						match other_object.get(key) {
+							Some(other_member) => stack.push((member, other_member)),
+							None => return false,
+						}
+					}
+				},
+				(Json::Array(_) | Json::Object(_), _) => return false,
+				(value, other_value) if value != other_value => return false,
+				_ => (),
+			}
+		}
+		return true;
+	}
 }
 
 
-enum Token
+impl FromIterator<Json> for Json
 {
-	ArrayBegin,
-	ArrayEnd,
-	Boolean(bool),
-	Colon,
-	Comma,
-	Null,
-	Number(f64),
-	ObjectBegin,
-	ObjectEnd,
-	String(String),
+	/// Collect the values into an Array
+	fn from_iter<I: IntoIterator<Item = Json>>(values: I) -> Json
+	{
+		return Json::Array(values.into_iter().collect());
+	}
 }
 
 
-impl Json
+impl FromIterator<(String, Json)> for Json
 {
-	// TODO redo with only a few allocations, which can be reused
-	// TODO add tests like from https://github.com/nst/JSONTestSuite
-	/// Parse a JSON value in linear time if the data is valid JSON
-	pub fn parse(bytes: &[u8]) -> Option<Json>
+	/// Collect the members into an Object, where the last member wins if the
+	/// keys are the same
+	fn from_iter<I: IntoIterator<Item = (String, Json)>>(members: I) -> Json
 	{
-		return parse(&mut tokenize(bytes)?);
+		return Json::Object(members.into_iter().collect());
 	}
 }
 
 
-/// Tokenize the entire content, otherwise `None`
-fn tokenize(bytes: &[u8]) -> Option<Vec<Token>>
+impl Clone for Json
 {
-	let mut tokens = Vec::<Token>::new();
-	let mut i = 0;
+	/// Clone the value without recursion, so the depth is unlimited
+	fn clone(&self) -> Json
+	{
+		/// A container being cloned, with the key of the member being cloned
+		enum Frame<'a>
+		{
+			Array(core::slice::Iter<'a, Json>, Vec<Json>),
+			Object(std::collections::btree_map::Iter<'a, String, Json>, BTreeMap<String, Json>, String),
+		}
 
-	while i < bytes.len() {
-		let byte = bytes[i];
-		let mut token_len = 1;
-		let token = match byte {
-			b'\t' | b'\n' | b'\r' | b' ' => {
-				i += 1;
-				continue;
-			},
-			b'[' => Token::ArrayBegin,
-			b']' => Token::ArrayEnd,
-			b't' => match peek_keyword(&bytes[i..bytes.len()], b"true") {
-				0 => return None,
-				peeked_len => {
-					token_len = peeked_len;
-					Token::Boolean(true)
-				},
-			},
-			b'f' => match peek_keyword(&bytes[i..bytes.len()], b"false") {
-				0 => return None,
-				peeked_len => {
-					token_len = peeked_len;
-					Token::Boolean(false)
+		let mut stack = Vec::<Frame>::new();
+		let mut next = self;
+
+		loop {
+			// Begin the container, otherwise clone the scalar
+			let mut value = match next {
+				Json::Array(array) => {
+					stack.push(Frame::Array(array.iter(), Vec::with_capacity(array.len())));
+					None
 				},
-			},
-			b':' => Token::Colon,
-			b',' => Token::Comma,
-			b'n' => match peek_keyword(&bytes[i..bytes.len()], b"null") {
-				0 => return None,
-				peeked_len => {
-					token_len = peeked_len;
-					Token::Null
+				Json::Boolean(boolean) => Some(Json::Boolean(*boolean)),
+				Json::Null => Some(Json::Null),
+				Json::Number(number) => Some(Json::Number(*number)),
+				Json::Object(object) => {
+					stack.push(Frame::Object(object.iter(), BTreeMap::new(), String::new()));
+					None
 				},
-			},
-			b'-' | b'0'..=b'9' => match peek_number(&bytes[i..bytes.len()]) {
-				(0, _) => return None,
-				(peeked_len, number) => {
-					token_len = peeked_len;
-					Token::Number(number)
-				}
-			},
-			b'{' => Token::ObjectBegin,
-			b'}' => Token::ObjectEnd,
-			b'"' => match peek_string(&bytes[i..bytes.len()]) {
-				(0, _) => return None,
-				(peeked_len, string) => {
-					token_len = peeked_len;
-					Token::String(string)
+				Json::String(string) => Some(Json::String(string.clone())),
+			};
+
+			// Put each complete value into its container until the next value
+			loop {
+				let found = match stack.last_mut() {
+					None => match value {
+						Some(value) => return value,
+						None => unreachable!(),
+					},
+					Some(Frame::Array(elements, array)) => {
+						if let Some(value) = value.take() {
+							array.push(value);
+						}
+						elements.next()
+					},
+					Some(Frame::Object(members, object, key)) => {
+						if let Some(value) = value.take() {
+							object.insert(core::mem::take(key), value);
+						}
+						members.next().map(|(member_key, member)| {
+							key.clone_from(member_key);
+							member
+						})
+					},
+				};
+				if let Some(found) = found {
+					next = found;
+					break;
 				}
-			},
-			_ => return None,
+				value = match stack.pop() {
+					Some(Frame::Array(_, array)) => Some(Json::Array(array)),
+					Some(Frame::Object(_, object, _)) => Some(Json::Object(object)),
+					None => unreachable!(),
+				};
+			}
+		}
+	}
+}
+
+
+impl PartialEq for Json
+{
+	/// Compare the values without recursion, so the depth is unlimited
+	fn eq(&self, other: &Json) -> bool
+	{
+		let mut stack = vec![(self, other)];
+		while let Some(pair) = stack.pop() {
+			match pair {
+				(Json::Array(array), Json::Array(other_array)) => {
+					if array.len() != other_array.len() {
+						return false;
+					}
+					stack.extend(array.iter().zip(other_array));
+				},
+				(Json::Object(object), Json::Object(other_object)) => {
+					if object.len() != other_object.len() {
+						return false;
+					}
+					for ((key, member), (other_key, other_member)) in object.iter().zip(other_object) {
+						if key != other_key {
+							return false;
+						}
+						stack.push((member, other_member));
+					}
+				},
+				(Json::Boolean(boolean), Json::Boolean(other_boolean)) if boolean == other_boolean => (),
+				(Json::Null, Json::Null) => (),
+				(Json::Number(number), Json::Number(other_number)) if number == other_number => (),
+				(Json::String(string), Json::String(other_string)) if string == other_string => (),
+				_ => return false,
+			}
+		}
+		return true;
+	}
+}
+
+
+impl Drop for Json
+{
+	/// Drop the nested values without recursion, so the depth is unlimited
+	fn drop(&mut self)
+	{
+		let mut stack = Vec::<Json>::new();
+		match self {
+			Json::Array(array) => stack.append(array),
+			Json::Object(object) => stack.extend(core::mem::take(object).into_values()),
+			_ => return,
+		}
+		// Each value is dropped after its nested values are moved to the stack
+		while let Some(mut value) = stack.pop() {
+			match &mut value {
+				Json::Array(array) => stack.append(array),
+				Json::Object(object) => stack.extend(core::mem::take(object).into_values()),
+				_ => (),
+			}
+		}
+	}
+}
+
+
+impl fmt::Display for ParseError
+{
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result
+	{
+		let reason = match self.kind {
+			ParseErrorKind::ControlCharacter(byte) => return write!(formatter, "control character 0x{:02x} at byte {}", byte, self.offset),
+			ParseErrorKind::DuplicateKey => "duplicate key",
+			ParseErrorKind::InvalidSurrogate => "invalid surrogate",
+			ParseErrorKind::InvalidToken => "invalid token",
+			ParseErrorKind::InvalidUtf8 => "invalid UTF-8",
+			ParseErrorKind::KeyTooLong => "key too long",
+			ParseErrorKind::MemoryLimit => "memory limit",
+			ParseErrorKind::NumberTooLarge => "number too large",
+			ParseErrorKind::Read => "read error",
+			ParseErrorKind::TooManyTokens => "too many tokens",
+			ParseErrorKind::TopLevelScalar => "top-level scalar",
+			ParseErrorKind::TrailingData => "trailing data",
+			ParseErrorKind::UnexpectedEnd => "unexpected end",
+			ParseErrorKind::UnexpectedToken => "unexpected token",
 		};
+		return write!(formatter, "{} at byte {}", reason, self.offset);
+	}
+}
+
+
+impl std::error::Error for ParseError
+{
+}
+
+
+/// Tokenize the entire content with the byte offset of each token, otherwise
+/// stop at the first invalid token
+fn tokenize(bytes: &[u8], tokens: &mut Vec<(usize, Token)>, options: &ParseOptions) -> Result<(), ParseError>
+{
+	let mut i = match options.allow_bom && bytes.starts_with(b"\xef\xbb\xbf") {
+		true => 3,
+		false => 0,
+	};
+	let mut total_bytes = 0;
+
+	while i < bytes.len() {
+		let byte = bytes[i];
+		if matches!(byte, b'\t' | b'\n' | b'\r' | b' ') || options.extra_whitespace.contains(&byte) {
+			i += 1;
+			continue;
+		}
+		if byte == b'/' && options.allow_comments {
+			match peek_comment(&bytes[i..bytes.len()]) {
+				0 => return Err(ParseError { kind: ParseErrorKind::InvalidToken, offset: i }),
+				comment_len => i += comment_len,
+			}
+			continue;
+		}
+		let (token_len, token) = match peek_token(&bytes[i..bytes.len()], options) {
+			Ok(peeked) => peeked,
+			Err(error) => return Err(ParseError { kind: error.kind, offset: i + error.offset }),
+		};
+		// Count the token, the value it may become, and the string
+		if let Some(max_total_bytes) = options.max_total_bytes {
+			total_bytes += core::mem::size_of::<(usize, Token)>() + core::mem::size_of::<Json>();
+			if let Token::Identifier(string) | Token::String(string) = &token {
+				total_bytes += string.len();
+			}
+			if total_bytes > max_total_bytes {
+				return Err(ParseError { kind: ParseErrorKind::MemoryLimit, offset: i });
+			}
+		}
+		if options.max_tokens.is_some_and(|max_tokens| tokens.len() >= max_tokens) {
+			return Err(ParseError { kind: ParseErrorKind::TooManyTokens, offset: i });
+		}
+		// A keyword before a colon is an unquoted key, like `{true: 1}`
+		if let (true, Token::Colon, Some((key_offset, key))) = (options.allow_unquoted_keys, &token, tokens.last_mut()) {
+			let key_bytes = &bytes[*key_offset..bytes.len()];
+			if matches!(key, Token::Boolean(_) | Token::Null | Token::Number(_)) && matches!(key_bytes[0], b'$' | b'A'..=b'Z' | b'_' | b'a'..=b'z') {
+				let key_len = peek_identifier(key_bytes);
+				*key = Token::Identifier(unsafe { String::from_utf8_unchecked(key_bytes[0..key_len].to_vec()) });
+			}
+		}
+		tokens.push((i, token));
 		i += token_len;
-		tokens.push(token);
 	}
 
-	return Some(tokens);
+	return Ok(());
+}
+
+
+/// Find the token at the start, which isn't whitespace, and return the bytes
+/// peeked and the token, otherwise the error with the offset from the start
+fn peek_token(remaining_bytes: &[u8], options: &ParseOptions) -> Result<(usize, Token), ParseError>
+{
+	let invalid_token = ParseError { kind: ParseErrorKind::InvalidToken, offset: 0 };
+	let mut token_len = 1;
+	let byte = remaining_bytes[0];
+	let token = match byte {
+		b'$' | b'A'..=b'Z' | b'_' | b'a'..=b'z' if options.allow_unquoted_keys => {
+			token_len = peek_identifier(remaining_bytes);
+			match &remaining_bytes[0..token_len] {
+				b"true" => Token::Boolean(true),
+				b"false" => Token::Boolean(false),
+				b"null" => Token::Null,
+				b"Infinity" | b"NaN" if options.allow_non_finite => return peek_number_token(remaining_bytes, options),
+				identifier => Token::Identifier(unsafe { String::from_utf8_unchecked(identifier.to_vec()) }),
+			}
+		},
+		b'[' => Token::ArrayBegin,
+		b']' => Token::ArrayEnd,
+		b't' => match peek_keyword(remaining_bytes, b"true") {
+			0 => return Err(invalid_token),
+			peeked_len => {
+				token_len = peeked_len;
+				Token::Boolean(true)
+			},
+		},
+		b'f' => match peek_keyword(remaining_bytes, b"false") {
+			0 => return Err(invalid_token),
+			peeked_len => {
+				token_len = peeked_len;
+				Token::Boolean(false)
+			},
+		},
+		b':' => Token::Colon,
+		b',' => Token::Comma,
+		b'n' => match peek_keyword(remaining_bytes, b"null") {
+			0 => return Err(invalid_token),
+			peeked_len => {
+				token_len = peeked_len;
+				Token::Null
+			},
+		},
+		b'+' | b'-' | b'.' | b'0'..=b'9' if byte == b'-' || byte.is_ascii_digit() || options.allow_json5_numbers => {
+			return peek_number_token(remaining_bytes, options);
+		},
+		b'I' | b'N' if options.allow_non_finite => return peek_number_token(remaining_bytes, options),
+		b'{' => Token::ObjectBegin,
+		b'}' => Token::ObjectEnd,
+		b'"' | b'\'' if byte == b'"' || options.allow_single_quotes => match peek_string(remaining_bytes, options) {
+			Err((offset, kind)) => return Err(ParseError { kind, offset }),
+			Ok((peeked_len, string)) => {
+				token_len = peeked_len;
+				Token::String(string)
+			}
+		},
+		_ => return Err(invalid_token),
+	};
+	return Ok((token_len, token));
+}
+
+
+/// Find the number token at the start and return the bytes peeked and the
+/// token, otherwise the error with the offset from the start
+fn peek_number_token(remaining_bytes: &[u8], options: &ParseOptions) -> Result<(usize, Token), ParseError>
+{
+	return match peek_number(remaining_bytes, options) {
+		(0, _) => Err(ParseError { kind: ParseErrorKind::InvalidToken, offset: 0 }),
+		(_, number) if options.max_number_abs.is_some_and(|max_number_abs| number.is_nan() || number.abs() > max_number_abs) => {
+			Err(ParseError { kind: ParseErrorKind::NumberTooLarge, offset: 0 })
+		},
+		(peeked_len, number) => match options.normalize_negative_zero && number == 0.0 {
+			true => Ok((peeked_len, Token::Number(0.0))),
+			false => Ok((peeked_len, Token::Number(number))),
+		},
+	};
+}
+
+
+/// Find a comment at the start, assuming the first byte is `/`, and return the
+/// bytes peeked, otherwise `0` if it isn't a comment or the block doesn't end
+fn peek_comment(remaining_bytes: &[u8]) -> usize
+{
+	return match remaining_bytes.get(1) {
+		Some(b'/') => remaining_bytes.iter().take_while(|&&byte| byte != b'\n').count(),
+		Some(b'*') => match remaining_bytes[2..remaining_bytes.len()].windows(2).position(|pair| pair == b"*/") {
+			Some(position) => position + 4,
+			None => 0,
+		},
+		_ => 0,
+	};
 }
 
 
@@ -117,29 +1619,73 @@ fn peek_keyword(remaining_bytes: &[u8], keyword: &[u8]) -> usize
 }
 
 
+/// Find a JSON5 identifier at the start, assuming the first byte is valid, and
+/// return the bytes peeked
+fn peek_identifier(remaining_bytes: &[u8]) -> usize
+{
+	return remaining_bytes.iter().take_while(|&&byte| matches!(byte, b'$' | b'0'..=b'9' | b'A'..=b'Z' | b'_' | b'a'..=b'z')).count();
+}
+
+
+/// Get the bytes without the JSON whitespace at the start and end
+fn trim_whitespace(bytes: &[u8]) -> &[u8]
+{
+	let is_whitespace = |byte: &u8| matches!(byte, b'\t' | b'\n' | b'\r' | b' ');
+	let start = bytes.iter().take_while(|byte| is_whitespace(byte)).count();
+	let end = bytes.len() - bytes[start..bytes.len()].iter().rev().take_while(|byte| is_whitespace(byte)).count();
+	return &bytes[start..end];
+}
+
+
 /// Find a JSON number at the start and return the bytes peeked and value,
 /// otherwise `(0, 0)`
-fn peek_number(remaining_bytes: &[u8]) -> (usize, f64)
+fn peek_number(remaining_bytes: &[u8], options: &ParseOptions) -> (usize, f64)
 {
 	// Regular expression:
 	// -?(0|1-9\d*)(\.\d+)?([eE][+-]?\d+)?
 
+	// Regular expression with JSON5 numbers:
+	// [+-]?((0|1-9\d*)\.?\d*|\.\d+)([eE][+-]?\d+)?
+	// [+-]?0[xX][\da-fA-F]+
+
 	enum State
 	{
 		Start,
 		Negative,
+		Positive,
 		IntegerZero,
 		IntegerNonZero,
 		IntegerDigits,
+		LeadingDot,
 		Dot,
 		FractionDigits,
 		E,
 		Sign,
 		ExponentDigits,
+		HexX,
+		HexDigits,
 	}
 
 	const INVALID_RESULT: (usize, f64) = (0, 0.0);
 
+	let json5 = options.allow_json5_numbers;
+
+	// NaN and Infinity with an optional sign
+	if options.allow_non_finite {
+		let (sign_len, sign) = match remaining_bytes.first() {
+			Some(b'-') => (1, -1.0),
+			Some(b'+') if json5 => (1, 1.0),
+			_ => (0, 1.0),
+		};
+		let unsigned_bytes = &remaining_bytes[sign_len..remaining_bytes.len()];
+		if unsigned_bytes.starts_with(b"Infinity") {
+			return (sign_len + 8, sign * f64::INFINITY);
+		}
+		if unsigned_bytes.starts_with(b"NaN") {
+			return (sign_len + 3, f64::NAN);
+		}
+	}
+
 	let mut state = State::Start;
 	let mut i = 0;
 
@@ -147,18 +1693,23 @@ fn peek_number(remaining_bytes: &[u8]) -> (usize, f64)
 		state = match state {
 			State::Start => match byte {
 				b'-' => State::Negative,
+				b'+' if json5 => State::Positive,
+				b'.' if json5 => State::LeadingDot,
 				b'0' => State::IntegerZero,
 				b'1' ..= b'9' => State::IntegerNonZero,
 				_ => return INVALID_RESULT,
 			},
-			State::Negative => match byte {
+			State::Negative | State::Positive => match byte {
+				b'.' if json5 => State::LeadingDot,
 				b'0' => State::IntegerZero,
 				b'1' ..= b'9' => State::IntegerNonZero,
 				_ => return INVALID_RESULT,
 			},
 			State::IntegerZero => match byte {
+				b'0' ..= b'9' if options.allow_leading_zeros => State::IntegerDigits,
 				b'.' => State::Dot,
 				b'e' | b'E' => State::E,
+				b'x' | b'X' if json5 => State::HexX,
 				_ => break,
 			},
 			State::IntegerNonZero => match byte {
@@ -173,8 +1724,14 @@ fn peek_number(remaining_bytes: &[u8]) -> (usize, f64)
 				b'e' | b'E' => State::E,
 				_ => break,
 			},
+			State::LeadingDot => match byte {
+				b'0' ..= b'9' => State::FractionDigits,
+				_ => return INVALID_RESULT,
+			},
 			State::Dot => match byte {
 				b'0' ..= b'9' => State::FractionDigits,
+				b'e' | b'E' if json5 => State::E,
+				_ if json5 => break,
 				_ => return INVALID_RESULT,
 			},
 			State::FractionDigits => match byte {
@@ -195,10 +1752,26 @@ fn peek_number(remaining_bytes: &[u8]) -> (usize, f64)
 				b'0' ..= b'9' => State::ExponentDigits,
 				_ => break,
 			},
+			State::HexX => match byte {
+				b'0' ..= b'9' | b'A' ..= b'F' | b'a' ..= b'f' => State::HexDigits,
+				_ => return INVALID_RESULT,
+			},
+			State::HexDigits => match byte {
+				b'0' ..= b'9' | b'A' ..= b'F' | b'a' ..= b'f' => State::HexDigits,
+				_ => break,
+			},
 		};
 		i += 1;
 	}
 
+	// The bytes may end before the number is complete
+	match state {
+		State::IntegerZero | State::IntegerNonZero | State::IntegerDigits | State::FractionDigits | State::ExponentDigits => (),
+		State::Dot if json5 => (),
+		State::HexDigits => return (i, peek_hex_number(&remaining_bytes[0..i])),
+		_ => return INVALID_RESULT,
+	}
+
 	return match f64::from_str(unsafe { core::str::from_utf8_unchecked(&remaining_bytes[0..i]) }) {
 		Ok(number) => (i, number),
 		Err(_) => (0, 0.0),
@@ -206,27 +1779,49 @@ fn peek_number(remaining_bytes: &[u8]) -> (usize, f64)
 }
 
 
-/// Find a JSON string at the start and return the bytes peeked and value,
-/// otherwise `(0, String::new())`
-fn peek_string(remaining_bytes: &[u8]) -> (usize, String)
+/// Get the value of a valid JSON5 hexadecimal number, like `-0xFF`
+fn peek_hex_number(number_bytes: &[u8]) -> f64
+{
+	let mut value: f64 = 0.0;
+	for &byte in number_bytes.iter().skip_while(|&&byte| byte != b'x' && byte != b'X').skip(1) {
+		let digit = match byte {
+			b'0' ..= b'9' => byte - b'0',
+			b'A' ..= b'F' => byte - b'A' + 10,
+			_ => byte - b'a' + 10,
+		};
+		value = value * 16.0 + digit as f64;
+	}
+	return match number_bytes[0] {
+		b'-' => -value,
+		_ => value,
+	};
+}
+
+
+/// Find a JSON string at the start, quoted by its first byte, and return the
+/// bytes peeked and value, otherwise the offset and reason that it's invalid
+fn peek_string(remaining_bytes: &[u8], options: &ParseOptions) -> Result<(usize, String), (usize, ParseErrorKind)>
 {
 	const BACKSPACE_CHAR: u8 = 8;
 	const FORM_FEED_CHAR: u8 = 12;
 
-	const INVALID_RESULT: (usize, String) = (0, String::new());
+	const INVALID_RESULT: Result<(usize, String), (usize, ParseErrorKind)> = Err((0, ParseErrorKind::InvalidToken));
 
 	let mut i: usize = 0;
 	let mut result = Vec::<u8>::new();
+	let mut closed = false;
+	let quote = remaining_bytes[0];
 
 	let len = remaining_bytes.len();
 	while i < len {
 		match remaining_bytes[i] {
 			// Control characters
-			0 ..= 31 => return INVALID_RESULT,
+			byte @ 0 ..= 31 if !options.allow_raw_control_chars => return Err((i, ParseErrorKind::ControlCharacter(byte))),
 			// Quote
-			b'"' => {
+			byte if byte == quote => {
 				if i > 0 {
 					i += 1;
+					closed = true;
 					break;
 				}
 				i += 1;
@@ -236,33 +1831,56 @@ fn peek_string(remaining_bytes: &[u8]) -> (usize, String)
 				i += 1;
 				match remaining_bytes.get(i) {
 					Some(b'"') => result.push(b'"'),
+					Some(b'\'') if quote == b'\'' => result.push(b'\''),
 					Some(b'\\') => result.push(b'\\'),
+					Some(b'/') => result.push(b'/'),
 					Some(b'b') => result.push(BACKSPACE_CHAR),
 					Some(b'f') => result.push(FORM_FEED_CHAR),
 					Some(b'n') => result.push(b'\n'),
 					Some(b'r') => result.push(b'\r'),
 					Some(b't') => result.push(b'\t'),
 					Some(b'u') => {
-						// Convert the 4 hex characters to a code point
-						let mut code_point: u32 = 0;
-						const ASCII_ZERO: u32 = 48;
-						const ASCII_UPPER_A: u32 = 65;
-						const ASCII_LOWER_A: u32 = 97;
-						const SHIFTS: [u32; 4] = [12, 8, 4, 0];
-						for shift in SHIFTS {
-							i += 1;
-							match remaining_bytes.get(i) {
-								Some(&byte @ b'0'..=b'9') => code_point += (byte as u32 - ASCII_ZERO) << shift,
-								Some(&byte @ b'A'..=b'F') => code_point += (byte as u32 - ASCII_UPPER_A + 10) << shift,
-								Some(&byte @ b'a'..=b'f') => code_point += (byte as u32 - ASCII_LOWER_A + 10) << shift,
-								_ => return INVALID_RESULT,
-							}
-						}
-						// Convert the code point to UTF-8 bytes
-						let c = unsafe { char::from_u32_unchecked(code_point) };
-						let mut buffer: [u8; 3] = [0, 0, 0];
-						for &byte in c.encode_utf8(&mut buffer).as_bytes() {
-							result.push(byte);
+						let escape_offset = i - 1;
+						let invalid_surrogate = Err((escape_offset, ParseErrorKind::InvalidSurrogate));
+						let code_point = match peek_hex_code_point(&remaining_bytes[i + 1..len]) {
+							Some(code_point) => code_point,
+							None => return INVALID_RESULT,
+						};
+						i += 4;
+						match code_point {
+							// Convert a character of the Basic Multilingual Plane
+							// to UTF-8 bytes directly, which is the common case
+							0 ..= 0x7F => result.push(code_point as u8),
+							0x80 ..= 0x7FF => {
+								result.push(0xC0 | (code_point >> 6) as u8);
+								result.push(0x80 | (code_point & 0x3F) as u8);
+							},
+							0x800 ..= 0xD7FF | 0xE000 ..= 0xFFFF => {
+								result.push(0xE0 | (code_point >> 12) as u8);
+								result.push(0x80 | (code_point >> 6 & 0x3F) as u8);
+								result.push(0x80 | (code_point & 0x3F) as u8);
+							},
+							// Combine the high surrogate with the low surrogate
+							// after it
+							0xD800 ..= 0xDBFF => {
+								let low_surrogate = match remaining_bytes.get(i + 1..i + 3) {
+									Some(b"\\u") => peek_hex_code_point(&remaining_bytes[i + 3..len]),
+									_ => None,
+								};
+								let c = match low_surrogate {
+									Some(low_surrogate @ 0xDC00 ..= 0xDFFF) => char::from_u32(0x10000 + ((code_point - 0xD800) << 10) + (low_surrogate - 0xDC00)),
+									_ => None,
+								};
+								let c = match c {
+									Some(c) => c,
+									None => return invalid_surrogate,
+								};
+								i += 6;
+								let mut buffer: [u8; 4] = [0, 0, 0, 0];
+								result.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+							},
+							// A low surrogate alone isn't a character
+							_ => return invalid_surrogate,
 						}
 					},
 					_ => return INVALID_RESULT,
@@ -277,9 +1895,162 @@ fn peek_string(remaining_bytes: &[u8]) -> (usize, String)
 		}
 	}
 
-	return match String::from_utf8(result) {
-		Ok(result) => (i, result),
-		Err(_) => INVALID_RESULT,
+	if !closed {
+		return Err((len, ParseErrorKind::UnexpectedEnd));
+	}
+
+	// Escape sequences are ASCII and become whole characters, so the result is
+	// valid UTF-8 if and only if the original bytes are
+	return match core::str::from_utf8(&remaining_bytes[0..i]) {
+		Ok(_) => Ok((i, unsafe { String::from_utf8_unchecked(result) })),
+		Err(error) => Err((error.valid_up_to(), ParseErrorKind::InvalidUtf8)),
+	};
+}
+
+
+/// Get the code point of the 4 hexadecimal digits at the start, otherwise
+/// `None`
+fn peek_hex_code_point(remaining_bytes: &[u8]) -> Option<u32>
+{
+	let mut code_point = 0;
+	for i in 0..4 {
+		let digit = match *remaining_bytes.get(i)? {
+			byte @ b'0' ..= b'9' => byte - b'0',
+			byte @ b'A' ..= b'F' => byte - b'A' + 10,
+			byte @ b'a' ..= b'f' => byte - b'a' + 10,
+			_ => return None,
+		};
+		code_point = code_point << 4 | digit as u32;
+	}
+	return Some(code_point);
+}
+
+
+/// Get the result of parsing the tokens, unless tokenizing failed first
+fn earliest_error<T>(tokenize_result: Result<(), ParseError>, parse_result: Result<T, ParseError>) -> Result<T, ParseError>
+{
+	return match tokenize_result {
+		Ok(()) => parse_result,
+		// Report the earliest error, since the valid tokens come first
+		Err(error) => match parse_result {
+			Ok(_) => Err(ParseError { kind: ParseErrorKind::TrailingData, offset: error.offset }),
+			Err(ParseError { kind: ParseErrorKind::UnexpectedEnd, .. }) => Err(error),
+			Err(parse_error) => Err(parse_error),
+		},
+	};
+}
+
+
+/// Parse the JSON value like `Json::parse_with`, and find more from the valid
+/// tokens before their strings are consumed, with the index of the last token
+/// of each value by the index of its first token. The tokens are empty for
+/// empty data which is Null.
+fn parse_and_find<T>(bytes: &[u8], options: &ParseOptions, find: impl FnOnce(&[(usize, Token)], &[usize]) -> T) -> Result<(Json, T), ParseError>
+{
+	let mut tokens = Vec::<(usize, Token)>::new();
+	let tokenize_result = tokenize(bytes, &mut tokens, options);
+	if options.empty_as_null && tokenize_result.is_ok() && tokens.is_empty() {
+		return Ok((Json::Null, find(&tokens, &[])));
+	}
+	let found = match (&tokenize_result, find_value_ends(&tokens, bytes.len(), options)) {
+		(Ok(()), Ok(ends)) => Ok(find(&tokens, &ends)),
+		(_, Err(error)) => Err(error),
+		(Err(error), _) => Err(*error),
+	};
+	let value = earliest_error(tokenize_result, parse(&mut tokens, bytes.len(), options))?;
+	return Ok((value, found?));
+}
+
+
+/// Check the grammar without building the value, and get the index of the
+/// last token of each value by the index of its first token
+fn find_value_ends(tokens: &[(usize, Token)], len: usize, options: &ParseOptions) -> Result<Vec<usize>, ParseError>
+{
+	enum State
+	{
+		Value,
+		ArrayFirst,
+		ArrayComma,
+		ArrayNext,
+		ObjectFirst,
+		ObjectKey,
+		ObjectColon,
+		ObjectNext,
+		Done,
+	}
+
+	let mut ends = vec![0; tokens.len()];
+	let mut begins = Vec::<usize>::new();
+	let mut state = State::Value;
+
+	for (i, (offset, token)) in tokens.iter().enumerate() {
+		let offset = *offset;
+		let unexpected_token = ParseError { kind: ParseErrorKind::UnexpectedToken, offset };
+		let value_end = match (state, token) {
+			(State::Done, _) => return Err(ParseError { kind: ParseErrorKind::TrailingData, offset }),
+			// [ [
+			// [ {
+			(State::Value | State::ArrayFirst | State::ArrayComma, Token::ArrayBegin) => {
+				begins.push(i);
+				state = State::ArrayFirst;
+				continue;
+			},
+			(State::Value | State::ArrayFirst | State::ArrayComma, Token::ObjectBegin) => {
+				begins.push(i);
+				state = State::ObjectFirst;
+				continue;
+			},
+			// [ value
+			// , value
+			(State::Value | State::ArrayFirst | State::ArrayComma, Token::Boolean(_) | Token::Null | Token::Number(_) | Token::String(_)) => i,
+			// [ ]
+			// value ]
+			(State::ArrayFirst | State::ArrayNext, Token::ArrayEnd) => begins.pop().unwrap_or(0),
+			// , ]
+			(State::ArrayComma, Token::ArrayEnd) if options.allow_trailing_commas => begins.pop().unwrap_or(0),
+			// value ,
+			(State::ArrayNext, Token::Comma) => {
+				state = State::ArrayComma;
+				continue;
+			},
+			// { }
+			// value }
+			(State::ObjectFirst | State::ObjectNext, Token::ObjectEnd) => begins.pop().unwrap_or(0),
+			// , }
+			(State::ObjectKey, Token::ObjectEnd) if options.allow_trailing_commas => begins.pop().unwrap_or(0),
+			// { "key"
+			// , "key"
+			(State::ObjectFirst | State::ObjectKey, Token::Identifier(_) | Token::String(_)) => {
+				state = State::ObjectColon;
+				continue;
+			},
+			// "key" :
+			(State::ObjectColon, Token::Colon) => {
+				state = State::Value;
+				continue;
+			},
+			// value ,
+			(State::ObjectNext, Token::Comma) => {
+				state = State::ObjectKey;
+				continue;
+			},
+			_ => return Err(unexpected_token),
+		};
+
+		// Remember where the complete value ends and continue its container
+		ends[value_end] = i;
+		state = match begins.last() {
+			None => State::Done,
+			Some(&begin) => match tokens[begin].1 {
+				Token::ArrayBegin => State::ArrayNext,
+				_ => State::ObjectNext,
+			},
+		};
+	}
+
+	return match state {
+		State::Done => Ok(ends),
+		_ => Err(ParseError { kind: ParseErrorKind::UnexpectedEnd, offset: len }),
 	};
 }
 
@@ -305,8 +2076,8 @@ unsafe fn get_map(value: &mut Json) -> *mut BTreeMap<String, Json>
 
 
 /// Parse the JSON value while consuming the strings already allocated,
-/// otherwise `None`
-fn parse(tokens: &mut [Token]) -> Option<Json>
+/// otherwise the error
+fn parse(tokens: &mut [(usize, Token)], len: usize, options: &ParseOptions) -> Result<Json, ParseError>
 {
 	enum State
 	{
@@ -315,9 +2086,9 @@ fn parse(tokens: &mut [Token]) -> Option<Json>
 		ArrayComma(*mut Vec<Json>),
 		ArrayValue(*mut Vec<Json>),
 		ObjectBegin(*mut BTreeMap<String, Json>),
-		ObjectColon(*mut BTreeMap<String, Json>, *mut String),
+		ObjectColon(*mut BTreeMap<String, Json>, *mut String, usize),
 		ObjectComma(*mut BTreeMap<String, Json>),
-		ObjectKey(*mut BTreeMap<String, Json>, *mut String),
+		ObjectKey(*mut BTreeMap<String, Json>, *mut String, usize),
 		ObjectValue(*mut BTreeMap<String, Json>),
 		RootValue,
 	}
@@ -326,7 +2097,25 @@ fn parse(tokens: &mut [Token]) -> Option<Json>
 
 	let mut stack: Vec<State> = vec![State::Start];
 
-	for token in tokens {
+	for (offset, token) in tokens {
+		let offset = *offset;
+		let unexpected_token = ParseError { kind: ParseErrorKind::UnexpectedToken, offset };
+		match stack.last() {
+			Some(State::RootValue) => return Err(ParseError { kind: ParseErrorKind::TrailingData, offset }),
+			Some(State::Start) if options.require_top_level_container => match token {
+				Token::Boolean(_) | Token::Null | Token::Number(_) | Token::String(_) => {
+					return Err(ParseError { kind: ParseErrorKind::TopLevelScalar, offset });
+				},
+				_ => (),
+			},
+			Some(State::ObjectBegin(_) | State::ObjectComma(_)) => match token {
+				Token::Identifier(key) | Token::String(key) if options.max_key_len.is_some_and(|max_key_len| key.len() > max_key_len) => {
+					return Err(ParseError { kind: ParseErrorKind::KeyTooLong, offset });
+				},
+				_ => (),
+			},
+			_ => (),
+		}
 		match token {
 			Token::ArrayBegin => match stack.last_mut() {
 				Some(state) => match state {
@@ -353,12 +2142,12 @@ fn parse(tokens: &mut [Token]) -> Option<Json>
 						stack.push(State::ArrayBegin(child_array));
 					},
 					// : [
-					State::ObjectColon(object, key) => {
+					State::ObjectColon(object, key, key_offset) => {
 						let object = unsafe { &mut**object };
 						let key = unsafe { &mut**key };
 						// Remember value
 						let array = match object.entry(core::mem::take(key)) {
-							Entry::Occupied(_) => return None,
+							Entry::Occupied(_) => return Err(ParseError { kind: ParseErrorKind::DuplicateKey, offset: *key_offset }),
 							Entry::Vacant(entry) => entry.insert(Json::Array(Vec::new())),
 						};
 						let array = unsafe { &mut*(array as *mut Json) };
@@ -368,9 +2157,9 @@ fn parse(tokens: &mut [Token]) -> Option<Json>
 						let array = unsafe { get_vec(array) };
 						stack.push(State::ArrayBegin(array));
 					},
-					_ => return None,
+					_ => return Err(unexpected_token),
 				},
-				_ => return None,
+				_ => return Err(unexpected_token),
 			},
 			Token::ArrayEnd => match stack.last() {
 				Some(state) => match state {
@@ -384,9 +2173,14 @@ fn parse(tokens: &mut [Token]) -> Option<Json>
 						// Pop state
 						stack.pop();
 					},
-					_ => return None,
+					// , ]
+					State::ArrayComma(_) if options.allow_trailing_commas => {
+						// Pop state
+						stack.pop();
+					},
+					_ => return Err(unexpected_token),
 				},
-				_ => return None,
+				_ => return Err(unexpected_token),
 			},
 			Token::Boolean(value) => match stack.last_mut() {
 				Some(state) => match state {
@@ -407,33 +2201,33 @@ fn parse(tokens: &mut [Token]) -> Option<Json>
 						*state = State::ArrayValue(array);
 					},
 					// : true
-					State::ObjectColon(object, key) => {
+					State::ObjectColon(object, key, key_offset) => {
 						let object = unsafe { &mut**object };
 						let key = unsafe { &mut**key };
 						// Remember value
 						match object.insert(core::mem::take(key), Json::Boolean(*value)) {
 							None => (),
-							Some(_old_value) => return None,
+							Some(_old_value) => return Err(ParseError { kind: ParseErrorKind::DuplicateKey, offset: *key_offset }),
 						}
 						// Replace state
 						*state = State::ObjectValue(object);
 					},
-					_ => return None,
+					_ => return Err(unexpected_token),
 				},
-				_ => return None,
+				_ => return Err(unexpected_token),
 			},
 			Token::Colon => match stack.last_mut() {
 				Some(state) => match state {
 					// "key" :
-					State::ObjectKey(object, key) => {
+					State::ObjectKey(object, key, key_offset) => {
 						let object = unsafe { &mut**object };
 						let key = unsafe { &mut**key };
 						// Replace state
-						*state = State::ObjectColon(object, key);
+						*state = State::ObjectColon(object, key, *key_offset);
 					},
-					_ => return None,
+					_ => return Err(unexpected_token),
 				},
-				_ => return None,
+				_ => return Err(unexpected_token),
 			},
 			Token::Comma => match stack.last_mut() {
 				Some(state) => match state {
@@ -447,9 +2241,22 @@ fn parse(tokens: &mut [Token]) -> Option<Json>
 						// Replace state
 						*state = State::ObjectComma(*object);
 					},
-					_ => return None,
+					_ => return Err(unexpected_token),
 				},
-				_ => return None,
+				_ => return Err(unexpected_token),
+			},
+			Token::Identifier(key) => match stack.last_mut() {
+				Some(state) => match state {
+					// { object_key
+					// , object_key
+					State::ObjectBegin(object) | State::ObjectComma(object) => {
+						let object = unsafe { &mut**object };
+						// Replace state
+						*state = State::ObjectKey(object, key, offset);
+					},
+					_ => return Err(unexpected_token),
+				},
+				_ => return Err(unexpected_token),
 			},
 			Token::Null => match stack.last_mut() {
 				Some(state) => match state {
@@ -470,20 +2277,20 @@ fn parse(tokens: &mut [Token]) -> Option<Json>
 						*state = State::ArrayValue(array);
 					},
 					// : null
-					State::ObjectColon(object, key) => {
+					State::ObjectColon(object, key, key_offset) => {
 						let object = unsafe { &mut**object };
 						let key = unsafe { &mut**key };
 						// Remember value
 						match object.insert(core::mem::take(key), Json::Null) {
 							None => (),
-							Some(_old_value) => return None,
+							Some(_old_value) => return Err(ParseError { kind: ParseErrorKind::DuplicateKey, offset: *key_offset }),
 						}
 						// Replace state
 						*state = State::ObjectValue(object);
 					},
-					_ => return None,
+					_ => return Err(unexpected_token),
 				},
-				_ => return None,
+				_ => return Err(unexpected_token),
 			},
 			Token::Number(value) => match stack.last_mut() {
 				Some(state) => match state {
@@ -504,20 +2311,20 @@ fn parse(tokens: &mut [Token]) -> Option<Json>
 						*state = State::ArrayValue(array);
 					},
 					// : 123
-					State::ObjectColon(object, key) => {
+					State::ObjectColon(object, key, key_offset) => {
 						let object = unsafe { &mut**object };
 						let key = unsafe { &mut**key };
 						// Remember value
 						match object.insert(core::mem::take(key), Json::Number(*value)) {
 							None => (),
-							Some(_old_value) => return None,
+							Some(_old_value) => return Err(ParseError { kind: ParseErrorKind::DuplicateKey, offset: *key_offset }),
 						}
 						// Replace state
 						*state = State::ObjectValue(object);
 					},
-					_ => return None,
+					_ => return Err(unexpected_token),
 				},
-				_ => return None,
+				_ => return Err(unexpected_token),
 			},
 			Token::ObjectBegin => match stack.last_mut() {
 				Some(state) => match state {
@@ -544,12 +2351,12 @@ fn parse(tokens: &mut [Token]) -> Option<Json>
 						stack.push(State::ObjectBegin(object));
 					},
 					// : {
-					State::ObjectColon(parent_object, key) => {
+					State::ObjectColon(parent_object, key, key_offset) => {
 						let parent_object = unsafe { &mut**parent_object };
 						let key = unsafe { &mut**key };
 						// Remember value
 						let child_object = match parent_object.entry(core::mem::take(key)) {
-							Entry::Occupied(_) => return None,
+							Entry::Occupied(_) => return Err(ParseError { kind: ParseErrorKind::DuplicateKey, offset: *key_offset }),
 							Entry::Vacant(entry) => entry.insert(Json::Object(BTreeMap::new())),
 						};
 						let child_object = unsafe { &mut*(child_object as *mut Json) };
@@ -559,9 +2366,9 @@ fn parse(tokens: &mut [Token]) -> Option<Json>
 						let child_object = unsafe { get_map(child_object) };
 						stack.push(State::ObjectBegin(child_object));
 					},
-					_ => return None,
+					_ => return Err(unexpected_token),
 				},
-				_ => return None,
+				_ => return Err(unexpected_token),
 			},
 			Token::ObjectEnd => match stack.last() {
 				Some(state) => match state {
@@ -575,9 +2382,14 @@ fn parse(tokens: &mut [Token]) -> Option<Json>
 						// Pop state
 						stack.pop();
 					},
-					_ => return None,
+					// , }
+					State::ObjectComma(_) if options.allow_trailing_commas => {
+						// Pop state
+						stack.pop();
+					},
+					_ => return Err(unexpected_token),
 				},
-				_ => return None,
+				_ => return Err(unexpected_token),
 			},
 			Token::String(value) => match stack.last_mut() {
 				Some(state) => match state {
@@ -598,13 +2410,13 @@ fn parse(tokens: &mut [Token]) -> Option<Json>
 						*state = State::ArrayValue(array);
 					},
 					// : "object_value"
-					State::ObjectColon(object, key) => {
+					State::ObjectColon(object, key, key_offset) => {
 						let object = unsafe { &mut**object };
 						let key = unsafe { &mut**key };
 						// Remember value
 						match object.insert(core::mem::take(key), Json::String(core::mem::take(value))) {
 							None => (),
-							Some(_old_value) => return None,
+							Some(_old_value) => return Err(ParseError { kind: ParseErrorKind::DuplicateKey, offset: *key_offset }),
 						}
 						// Replace state
 						*state = State::ObjectValue(object);
@@ -614,17 +2426,868 @@ fn parse(tokens: &mut [Token]) -> Option<Json>
 					State::ObjectBegin(object) | State::ObjectComma(object) => {
 						let object = unsafe { &mut**object };
 						// Replace state
-						*state = State::ObjectKey(object, value);
+						*state = State::ObjectKey(object, value, offset);
 					},
-					_ => return None,
+					_ => return Err(unexpected_token),
 				},
-				_ => return None,
+				_ => return Err(unexpected_token),
 			},
 		}
 	}
 
 	return match stack.last_mut() {
-		Some(State::RootValue) => Some(root_value),
-		_ => None,
+		Some(State::RootValue) => Ok(root_value),
+		_ => Err(ParseError { kind: ParseErrorKind::UnexpectedEnd, offset: len }),
 	};
 }
+
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	/// Parse the JSON data which must be valid
+	fn json(bytes: &[u8]) -> Json
+	{
+		return match Json::parse_detailed(bytes) {
+			Ok(value) => value,
+			Err(error) => panic!("{}", error),
+		};
+	}
+
+	#[test]
+	fn extra_whitespace_built_at_runtime()
+	{
+		let whitespace = vec![0x0B, 0x0C];
+		let options = ParseOptions { extra_whitespace: &whitespace, ..ParseOptions::default() };
+		let value = Json::parse_with(b"\x0C[1,\x0B2]\x0C", &options).unwrap();
+		assert!(value == Json::Array(vec![Json::Number(1.0), Json::Number(2.0)]));
+		assert_eq!(Json::parse_detailed(b"\x0C[1]").err(), Some(ParseError { kind: ParseErrorKind::InvalidToken, offset: 0 }));
+	}
+
+	#[test]
+	fn parse_into_replaces_root()
+	{
+		let mut root = Json::Array(vec![Json::Null; 4]);
+		Json::parse_into(b"[1, 2]", &mut root).unwrap();
+		assert!(root == Json::Array(vec![Json::Number(1.0), Json::Number(2.0)]));
+		Json::parse_into(b"\"a\"", &mut root).unwrap();
+		assert!(root == Json::String("a".to_string()));
+		assert!(Json::parse_into(b"[1,", &mut root).is_err());
+		assert!(root == Json::String("a".to_string()));
+	}
+
+	#[test]
+	fn parse_into_reuses_root()
+	{
+		let mut root = Json::Array(Vec::with_capacity(100));
+		Json::parse_into(b"[1, [2]]", &mut root).unwrap();
+		Json::parse_into(b"[3]", &mut root).unwrap();
+		match &root {
+			Json::Array(array) => assert!(array.capacity() >= 100 && array.len() == 1),
+			_ => panic!("expected an Array"),
+		}
+		assert_eq!(root.to_string(), "[3]");
+
+		let mut root = Json::String(String::with_capacity(100));
+		Json::parse_into(b"\"abc\"", &mut root).unwrap();
+		Json::parse_into(b"\"d\"", &mut root).unwrap();
+		match &root {
+			Json::String(string) => assert!(string.capacity() >= 100 && string == "d"),
+			_ => panic!("expected a String"),
+		}
+
+		let mut root = json(b"{\"a\": 1, \"b\": 2}");
+		Json::parse_into(b"{\"c\": [3]}", &mut root).unwrap();
+		assert_eq!(root.to_string(), "{\"c\":[3]}");
+		Json::parse_into(b"null", &mut root).unwrap();
+		assert!(root == Json::Null);
+	}
+
+	#[test]
+	fn into_containers_and_string()
+	{
+		assert_eq!(Json::String("a".to_string()).into_string(), Some("a".to_string()));
+		assert_eq!(Json::Null.into_string(), None);
+		assert_eq!(Json::Array(vec![Json::Null]).into_array().map(|array| array.len()), Some(1));
+		assert!(Json::Object(BTreeMap::new()).into_object().is_some_and(|object| object.is_empty()));
+		assert!(Json::Null.into_object().is_none());
+	}
+
+	// Regression tests from inputs which overflowed the stack or panicked
+	#[test]
+	fn fuzz_regressions()
+	{
+		let deep_array = "[".repeat(1_000_000);
+		assert!(Json::parse_detailed(deep_array.as_bytes()).is_err());
+
+		let deep_array = format!("{}{}", "[".repeat(1_000_000), "]".repeat(1_000_000));
+		let value = Json::parse_detailed(deep_array.as_bytes()).unwrap();
+		assert_eq!(value.to_string().len(), 2_000_000);
+		assert!(value.clone() == value);
+		drop(value);
+
+		let deep_object = format!("{}1{}", "{\"a\":".repeat(100_000), "}".repeat(100_000));
+		let value = Json::parse_detailed(deep_object.as_bytes()).unwrap();
+		assert_eq!(value.to_string().len(), deep_object.len());
+
+		let inputs: [&[u8]; 8] = [b"", b"\"", b"\"\\", b"\"\\u", b"\"\\ud800\\u", b"-", b"1e", b"\xff"];
+		for bytes in inputs {
+			assert!(Json::parse_detailed(bytes).is_err());
+		}
+	}
+
+	#[test]
+	fn strict_preset()
+	{
+		let options = ParseOptions::strict();
+		assert!(Json::parse_with(b"{\"a\": [1, 2.5e3, null]}", &options).is_ok());
+		let relaxed: [&[u8]; 7] = [b"[1,]", b"[1] // comment", b"\xef\xbb\xbf[]", b"NaN", b"{a: 1}", b"['a']", b"[01]"];
+		for bytes in relaxed {
+			assert!(Json::parse_with(bytes, &options).is_err());
+		}
+	}
+
+	#[test]
+	fn lenient_preset()
+	{
+		let options = ParseOptions::lenient();
+		let value = Json::parse_with(b"\xef\xbb\xbf// list\n[1, /* two */ 2, NaN, -Infinity,]", &options).unwrap();
+		assert_eq!(value.to_string(), "[1,2,null,null]");
+		let numbers = value.into_array().unwrap();
+		assert!(matches!(numbers[2], Json::Number(number) if number.is_nan()));
+		assert!(numbers[3] == Json::Number(f64::NEG_INFINITY));
+		assert!(Json::parse_with(b"{\"a\": 1,}", &options).is_ok());
+		assert!(Json::parse_with(b"[1] /* unterminated", &options).is_err());
+		assert!(Json::parse_with(b"[1,,]", &options).is_err());
+		let json5: [&[u8]; 7] = [b"['a']", b"{a: 1}", b"0x1F", b"+1", b"[007]", b"\"a\tb\"", b" /* nothing */ "];
+		for bytes in json5 {
+			assert!(Json::parse_with(bytes, &options).is_err());
+		}
+	}
+
+	#[test]
+	fn json5_preset()
+	{
+		let options = ParseOptions::json5();
+		let bytes = b"// config\n{unquoted: 'single', hex: 0xFF, lead: .5, plus: +1, inf: +Infinity, list: [1, 2,],}";
+		let value = Json::parse_with(bytes, &options).unwrap();
+		assert_eq!(value.to_string(), "{\"hex\":255,\"inf\":null,\"lead\":0.5,\"list\":[1,2],\"plus\":1,\"unquoted\":\"single\"}");
+		assert!(Json::parse_with(b"\xef\xbb\xbf{}", &options).is_err());
+		assert!(Json::parse_with(b"[01]", &options).is_err());
+	}
+
+	#[test]
+	fn value_counts_of_distinct_elements()
+	{
+		let value = Json::parse(b"[1, \"a\", {\"b\": [0]}, 1, -0, 0, {\"b\": [-0]}, \"a\", 1, null]").unwrap();
+		let counts = value.value_counts().unwrap();
+		let counts: Vec<(String, usize)> = counts.iter().map(|(element, count)| (element.to_string(), *count)).collect();
+		let expected = [("1", 3), ("\"a\"", 2), ("{\"b\":[0]}", 2), ("-0", 2), ("null", 1)];
+		assert_eq!(counts, expected.map(|(element, count)| (element.to_string(), count)));
+		assert!(Json::Null.value_counts().is_none());
+	}
+
+	#[test]
+	fn json5_keyword_keys()
+	{
+		let options = ParseOptions::json5();
+		let value = Json::parse_with(b"{true: 1, false: 2, null: 3, NaN: 4, Infinity: true, key: null}", &options).unwrap();
+		assert_eq!(value.to_string(), "{\"Infinity\":true,\"NaN\":4,\"false\":2,\"key\":null,\"null\":3,\"true\":1}");
+		assert!(Json::parse_with(b"{1: 2}", &options).is_err());
+		assert!(Json::parse_with(b"[true: 1]", &options).is_err());
+		assert!(Json::parse_detailed(b"{true: 1}").is_err());
+	}
+
+	#[test]
+	fn predicates()
+	{
+		let values = [json(b"[]"), json(b"true"), json(b"null"), json(b"1"), json(b"{}"), json(b"\"\"")];
+		for (i, value) in values.iter().enumerate() {
+			let predicates = [value.is_array(), value.is_boolean(), value.is_null(), value.is_number(), value.is_object(), value.is_string()];
+			assert_eq!(predicates.iter().filter(|&&is| is).count(), 1);
+			assert!(predicates[i]);
+		}
+	}
+
+	#[test]
+	fn get_or_insert_with()
+	{
+		let mut value = json(b"{}");
+		value.get_or_insert_with("a", || json(b"{}")).unwrap().get_or_insert_with("b", || Json::Number(1.0));
+		value.get_or_insert_with("a", || Json::Null).unwrap().get_or_insert_with("b", || Json::Number(2.0));
+		assert_eq!(value.to_string(), "{\"a\":{\"b\":1}}");
+		assert!(json(b"[]").get_or_insert_with("a", || Json::Null).is_none());
+	}
+
+	#[test]
+	fn deep_get()
+	{
+		let mut value = json(b"{\"a\": null, \"b\": {\"c\": [1]}}");
+		assert!(value.deep_get(&["b", "c"]).is_some_and(|c| *c == json(b"[1]")));
+		assert!(value.deep_get(&["b", "c", "0"]).is_none());
+		assert!(value.deep_get::<&str>(&[]).is_some_and(|root| *root == json(b"{\"a\": null, \"b\": {\"c\": [1]}}")));
+		*value.deep_get_mut(&["b".to_string(), "c".to_string()]).unwrap() = Json::Null;
+		assert_eq!(value.to_string(), "{\"a\":null,\"b\":{\"c\":null}}");
+		assert!(value.deep_get_mut(&["a", "b"]).is_none());
+	}
+
+	#[test]
+	fn trailing_data()
+	{
+		let error = |bytes: &[u8]| Json::parse_detailed(bytes).err();
+		assert_eq!(error(b"[1] [2]"), Some(ParseError { kind: ParseErrorKind::TrailingData, offset: 4 }));
+		assert_eq!(error(b"1 x"), Some(ParseError { kind: ParseErrorKind::TrailingData, offset: 2 }));
+		assert_eq!(error(b"{} }"), Some(ParseError { kind: ParseErrorKind::TrailingData, offset: 3 }));
+		assert_eq!(error(b"[1, 2"), Some(ParseError { kind: ParseErrorKind::UnexpectedEnd, offset: 5 }));
+		assert!(Json::parse(b" [1] \n").is_some());
+		assert!(Json::parse(b"[1] [2]").is_none());
+	}
+
+	#[test]
+	fn top_level_scalars()
+	{
+		let options = ParseOptions { require_top_level_container: true, ..ParseOptions::default() };
+		assert_eq!(Json::parse_with(b" 1", &options).err(), Some(ParseError { kind: ParseErrorKind::TopLevelScalar, offset: 1 }));
+		assert_eq!(Json::parse_with(b"\"a\"", &options).err(), Some(ParseError { kind: ParseErrorKind::TopLevelScalar, offset: 0 }));
+		assert!(Json::parse_with(b"[1]", &options).is_ok());
+		assert!(Json::parse_with(b"{\"a\": 1}", &options).is_ok());
+		assert!(Json::parse_with(b"1", &ParseOptions::default()).is_ok());
+	}
+
+	#[test]
+	fn map_strings_and_keys()
+	{
+		let mut value = json(b"{\"a\": [\"x\", {\"b\": \"y\"}], \"c\": 1}");
+		value.map_strings(str::to_uppercase);
+		assert_eq!(value.to_string(), "{\"a\":[\"X\",{\"b\":\"Y\"}],\"c\":1}");
+		value.map_keys(|key| format!("_{}", key));
+		assert_eq!(value.to_string(), "{\"_a\":[\"X\",{\"_b\":\"Y\"}],\"_c\":1}");
+		value.map_keys(|_| "k".to_string());
+		assert_eq!(value.to_string(), "{\"k\":1}");
+	}
+
+	#[test]
+	fn invalid_utf8()
+	{
+		let error = |bytes: &[u8]| Json::parse_detailed(bytes).err();
+		assert_eq!(error(b"\"a\xff\""), Some(ParseError { kind: ParseErrorKind::InvalidUtf8, offset: 2 }));
+		assert_eq!(error(b"[\"\xc3\"]"), Some(ParseError { kind: ParseErrorKind::InvalidUtf8, offset: 2 }));
+		assert_eq!(error(b"\"\xed\xa0\x80\""), Some(ParseError { kind: ParseErrorKind::InvalidUtf8, offset: 1 }));
+		assert!(json(b"\"\xc3\xa9\xf0\x9f\x98\x80\"") == Json::String("\u{e9}\u{1f600}".to_string()));
+		assert_eq!(format!("{}", ParseError { kind: ParseErrorKind::InvalidUtf8, offset: 2 }), "invalid UTF-8 at byte 2");
+	}
+
+	#[test]
+	fn deep_values_without_recursion()
+	{
+		let mut value = Json::Null;
+		for i in 0..200_000 {
+			value = match i % 2 {
+				0 => Json::Array(vec![value]),
+				_ => Json::Object(BTreeMap::from([("a".to_string(), value)])),
+			};
+		}
+		let clone = value.clone();
+		assert!(clone == value);
+		assert!(Json::parse(value.to_string().as_bytes()).is_some_and(|parsed| parsed == value));
+	}
+
+	#[test]
+	fn extend()
+	{
+		let mut array = json(b"[1]");
+		assert!(array.extend(json(b"[2, 3]")));
+		assert!(!array.extend(json(b"{}")));
+		assert_eq!(array.to_string(), "[1,2,3]");
+		let mut object = json(b"{\"a\": 1, \"b\": 2}");
+		assert!(object.extend(json(b"{\"b\": 3, \"c\": 4}")));
+		assert!(!object.extend(json(b"[]")));
+		assert_eq!(object.to_string(), "{\"a\":1,\"b\":3,\"c\":4}");
+		assert!(!json(b"1").extend(json(b"[]")));
+	}
+
+	#[test]
+	fn matches_shape()
+	{
+		let template = json(b"{\"id\": 0, \"tags\": [\"\"], \"owner\": {\"name\": \"\"}}");
+		assert!(json(b"{\"id\": 5, \"tags\": [\"a\", \"b\"], \"owner\": {\"name\": \"x\"}}").matches_shape(&template));
+		assert!(json(b"{\"id\": 5, \"tags\": [], \"owner\": {\"name\": \"x\"}}").matches_shape(&template));
+		assert!(!json(b"{\"id\": 5, \"tags\": [], \"owner\": {\"name\": \"x\"}, \"extra\": 1}").matches_shape(&template));
+		assert!(!json(b"{\"id\": \"5\", \"tags\": [], \"owner\": {\"name\": \"x\"}}").matches_shape(&template));
+		assert!(!json(b"{\"id\": 5, \"tags\": [1], \"owner\": {\"name\": \"x\"}}").matches_shape(&template));
+		assert!(!json(b"{\"id\": 5, \"tags\": []}").matches_shape(&template));
+		assert!(json(b"[1, 2]").matches_shape(&json(b"[]")));
+	}
+
+	#[test]
+	fn raw_control_chars()
+	{
+		let options = ParseOptions { allow_raw_control_chars: true, ..ParseOptions::default() };
+		assert!(Json::parse_with(b"\"a\tb\nc\x01\"", &options).is_ok_and(|value| value == Json::String("a\tb\nc\x01".to_string())));
+		assert_eq!(Json::parse_detailed(b"\"a\tb\"").err(), Some(ParseError { kind: ParseErrorKind::ControlCharacter(b'\t'), offset: 2 }));
+	}
+
+	#[test]
+	fn single_quotes()
+	{
+		let options = ParseOptions { allow_single_quotes: true, ..ParseOptions::default() };
+		let value = Json::parse_with(b"['it\\'s', 'say \"hi\"', \"a'b\"]", &options).unwrap();
+		assert_eq!(value.to_string(), "[\"it's\",\"say \\\"hi\\\"\",\"a'b\"]");
+		assert!(Json::parse_with(b"{'a': 1}", &options).is_ok_and(|value| value == json(b"{\"a\": 1}")));
+		assert!(Json::parse_with(b"'a", &options).is_err());
+		assert_eq!(Json::parse_detailed(b"['a']").err(), Some(ParseError { kind: ParseErrorKind::InvalidToken, offset: 1 }));
+	}
+
+	#[test]
+	fn unquoted_keys()
+	{
+		let options = ParseOptions { allow_unquoted_keys: true, ..ParseOptions::default() };
+		assert!(Json::parse_with(b"{$a_1: 2, b: [3]}", &options).is_ok_and(|value| value == json(b"{\"$a_1\": 2, \"b\": [3]}")));
+		assert!(Json::parse_with(b"[a]", &options).is_err());
+		assert!(Json::parse_with(b"{1a: 2}", &options).is_err());
+		assert_eq!(Json::parse_detailed(b"{a: 1}").err(), Some(ParseError { kind: ParseErrorKind::InvalidToken, offset: 1 }));
+	}
+
+	#[test]
+	fn json5_numbers()
+	{
+		let options = ParseOptions { allow_json5_numbers: true, ..ParseOptions::default() };
+		let value = Json::parse_with(b"[0x1F, 0XaB, -0x10, +1, .5, 5., +.5e1]", &options).unwrap();
+		assert_eq!(value.to_string(), "[31,171,-16,1,0.5,5,5]");
+		let invalid: [&[u8]; 4] = [b"0x", b"+", b".", b"0x1.5"];
+		for bytes in invalid {
+			assert!(Json::parse_with(bytes, &options).is_err());
+		}
+		assert_eq!(Json::parse_detailed(b"[+1]").err(), Some(ParseError { kind: ParseErrorKind::InvalidToken, offset: 1 }));
+		assert_eq!(Json::parse_detailed(b"[1.]").err(), Some(ParseError { kind: ParseErrorKind::InvalidToken, offset: 1 }));
+	}
+
+	#[test]
+	fn as_str_lossy()
+	{
+		assert!(matches!(json(b"\"a\"").as_str_lossy(), Some(Cow::Borrowed("a"))));
+		assert_eq!(json(b"12.5").as_str_lossy().as_deref(), Some("12.5"));
+		assert_eq!(json(b"true").as_str_lossy().as_deref(), Some("true"));
+		assert_eq!(json(b"null").as_str_lossy().as_deref(), Some("null"));
+		assert!(json(b"[]").as_str_lossy().is_none());
+		assert!(json(b"{}").as_str_lossy().is_none());
+	}
+
+	#[test]
+	fn keys_and_values()
+	{
+		let value = json(b"{\"b\": 1, \"a\": 3, \"c\": 2}");
+		assert_eq!(value.keys().unwrap().collect::<Vec<_>>(), ["a", "b", "c"]);
+		assert_eq!(value.values().unwrap().map(Json::to_string).collect::<Vec<_>>(), ["3", "1", "2"]);
+		assert!(json(b"{}").keys().is_some_and(|mut keys| keys.next().is_none()));
+		assert!(json(b"[]").keys().is_none() && json(b"[]").values().is_none());
+	}
+
+	#[test]
+	fn deep_merge_and_merge_all()
+	{
+		let mut base = json(b"{\"a\": {\"x\": 1, \"y\": [1, 2]}, \"b\": 1}");
+		base.deep_merge(json(b"{\"a\": {\"y\": [3], \"z\": null}, \"c\": 2}"));
+		assert_eq!(base.to_string(), "{\"a\":{\"x\":1,\"y\":[3],\"z\":null},\"b\":1,\"c\":2}");
+		let mut base = json(b"{\"a\": 1}");
+		base.deep_merge(json(b"[1]"));
+		assert_eq!(base.to_string(), "[1]");
+		let merged = Json::merge_all([json(b"{\"a\": 1, \"b\": {\"c\": 1}}"), json(b"{\"b\": {\"d\": 2}}"), json(b"{\"a\": 3}")]);
+		assert_eq!(merged.to_string(), "{\"a\":3,\"b\":{\"c\":1,\"d\":2}}");
+		assert!(Json::merge_all(Vec::new()) == Json::Null);
+	}
+
+	#[test]
+	fn from_iterators()
+	{
+		let array: Json = (1..=3).map(|i| Json::Number(i as f64)).collect();
+		assert_eq!(array.to_string(), "[1,2,3]");
+		let object: Json = [("b".to_string(), Json::Null), ("a".to_string(), Json::Null), ("b".to_string(), Json::Boolean(true))].into_iter().collect();
+		assert_eq!(object.to_string(), "{\"a\":null,\"b\":true}");
+		let empty: Json = core::iter::empty::<(String, Json)>().collect();
+		assert_eq!(empty.to_string(), "{}");
+	}
+
+	#[test]
+	fn max_total_bytes()
+	{
+		let options = ParseOptions { max_total_bytes: Some(1000), ..ParseOptions::default() };
+		assert!(Json::parse_with(b"[1, \"a\", {\"b\": null}]", &options).is_ok());
+		let whitespace = [b' '; 2000];
+		assert!(Json::parse_with(&whitespace, &ParseOptions { empty_as_null: true, ..options }).is_ok());
+		let long_string = format!("\"{}\"", "a".repeat(2000));
+		assert_eq!(Json::parse_with(long_string.as_bytes(), &options).err(), Some(ParseError { kind: ParseErrorKind::MemoryLimit, offset: 0 }));
+		let many_values = format!("[{}0]", "0,".repeat(1000));
+		assert!(Json::parse_with(many_values.as_bytes(), &options).is_err_and(|error| error.kind == ParseErrorKind::MemoryLimit));
+	}
+
+	#[test]
+	fn kinds()
+	{
+		let values = [json(b"[]"), json(b"true"), json(b"null"), json(b"1"), json(b"{}"), json(b"\"\"")];
+		let kinds = [JsonKind::Array, JsonKind::Boolean, JsonKind::Null, JsonKind::Number, JsonKind::Object, JsonKind::String];
+		let names = ["array", "boolean", "null", "number", "object", "string"];
+		for (i, value) in values.iter().enumerate() {
+			assert_eq!(value.kind(), kinds[i]);
+			assert_eq!(value.type_name(), names[i]);
+		}
+	}
+
+	#[test]
+	fn truthiness()
+	{
+		let falsy: [&[u8]; 5] = [b"false", b"null", b"0", b"-0", b"\"\""];
+		for bytes in falsy {
+			assert!(!json(bytes).is_truthy());
+		}
+		assert!(!Json::Number(f64::NAN).is_truthy());
+		assert!(json(b"[]").is_truthy() && json(b"{}").is_truthy() && json(b"\"0\"").is_truthy());
+		assert!(!json(b"[]").is_truthy_nonempty() && !json(b"{}").is_truthy_nonempty());
+		assert!(json(b"[0]").is_truthy_nonempty() && !json(b"0").is_truthy_nonempty());
+	}
+
+	#[test]
+	fn fold_numbers()
+	{
+		let value = json(b"{\"a\": [1, 2, {\"b\": 3}], \"c\": \"4\", \"d\": null}");
+		assert_eq!(value.fold_numbers(0.0, |sum, number| sum + number), 6.0);
+		assert_eq!(value.fold_numbers(Vec::new(), |mut numbers, number| { numbers.push(number); numbers }), [1.0, 2.0, 3.0]);
+		assert_eq!(json(b"5").fold_numbers(1.0, f64::max), 5.0);
+		assert_eq!(json(b"[]").fold_numbers(0, |count, _| count + 1), 0);
+	}
+
+	#[test]
+	fn escaped_forward_slash()
+	{
+		assert!(json(b"\"a\\/b\"") == Json::String("a/b".to_string()));
+		assert!(json(b"\"a/b\"") == Json::String("a/b".to_string()));
+		assert!(json(b"{\"\\/\": \"\\\\/\"}") == Json::Object(BTreeMap::from([("/".to_string(), Json::String("\\/".to_string()))])));
+		assert_eq!(Json::parse_detailed(b"\"\\x\"").err(), Some(ParseError { kind: ParseErrorKind::InvalidToken, offset: 0 }));
+	}
+
+	#[test]
+	fn leading_zeros()
+	{
+		let options = ParseOptions { allow_leading_zeros: true, ..ParseOptions::default() };
+		assert!(Json::parse_with(b"[007, -01, 0, 00.5, 010e1]", &options).is_ok_and(|value| value.to_string() == "[7,-1,0,0.5,100]"));
+		assert_eq!(Json::parse_detailed(b"[01]").err(), Some(ParseError { kind: ParseErrorKind::UnexpectedToken, offset: 2 }));
+	}
+
+	#[test]
+	fn empty_as_null()
+	{
+		let options = ParseOptions { empty_as_null: true, ..ParseOptions::default() };
+		assert!(Json::parse_with(b"", &options).is_ok_and(|value| value == Json::Null));
+		assert!(Json::parse_with(b" \n\t", &options).is_ok_and(|value| value == Json::Null));
+		assert!(Json::parse_with(b" 1 ", &options).is_ok_and(|value| value == Json::Number(1.0)));
+		assert!(Json::parse_with(b" x", &options).is_err());
+		assert_eq!(Json::parse_detailed(b"").err(), Some(ParseError { kind: ParseErrorKind::UnexpectedEnd, offset: 0 }));
+	}
+
+	#[test]
+	fn truncate_depth()
+	{
+		let mut value = json(b"{\"a\": [1, [2]], \"b\": 3}");
+		value.truncate_depth(1, Json::String("...".to_string()));
+		assert_eq!(value.to_string(), "{\"a\":[\"...\",\"...\"],\"b\":3}");
+		let mut value = json(b"[[1]]");
+		value.truncate_depth(0, Json::Null);
+		assert_eq!(value.to_string(), "[null]");
+		let mut value = json(b"[[1]]");
+		value.truncate_depth(2, Json::Null);
+		assert_eq!(value.to_string(), "[[1]]");
+	}
+
+	#[test]
+	fn entries_array()
+	{
+		let value = json(b"{\"b\": 2, \"a\": [1]}");
+		let entries = value.entries_to_array().unwrap();
+		assert_eq!(entries.to_string(), "[[\"a\",[1]],[\"b\",2]]");
+		assert!(Json::from_entries_array(entries).is_some_and(|object| object == value));
+		assert!(Json::from_entries_array(json(b"[[\"a\", 1], [\"a\", 2]]")).is_some_and(|object| object == json(b"{\"a\": 2}")));
+		assert!(Json::from_entries_array(json(b"[[1, 2]]")).is_none());
+		assert!(Json::from_entries_array(json(b"[[\"a\"]]")).is_none());
+		assert!(Json::from_entries_array(json(b"{}")).is_none());
+		assert!(json(b"[]").entries_to_array().is_none());
+	}
+
+	#[test]
+	fn all_finite()
+	{
+		assert!(json(b"{\"a\": [1, 2.5e300], \"b\": \"NaN\"}").all_finite());
+		assert!(!Json::Array(vec![Json::Number(1.0), Json::Array(vec![Json::Number(f64::NAN)])]).all_finite());
+		assert!(!Json::Number(f64::NEG_INFINITY).all_finite());
+	}
+
+	#[test]
+	fn for_each_element_mut()
+	{
+		let mut value = json(b"[1, 2, 3, 4]");
+		let mut visited = 0;
+		value.for_each_element_mut(|element| {
+			visited += 1;
+			*element = Json::Null;
+			match visited {
+				2 => ControlFlow::Break(()),
+				_ => ControlFlow::Continue(()),
+			}
+		});
+		assert_eq!(value.to_string(), "[null,null,3,4]");
+		let mut value = json(b"{\"a\": 1}");
+		value.for_each_element_mut(|_| ControlFlow::Break(()));
+		assert_eq!(value.to_string(), "{\"a\":1}");
+	}
+
+	#[test]
+	fn max_key_len()
+	{
+		let options = ParseOptions { max_key_len: Some(2), ..ParseOptions::default() };
+		assert!(Json::parse_with(b"{\"ab\": {\"c\": 1}}", &options).is_ok());
+		assert_eq!(Json::parse_with(b"{\"ab\": 1, \"abc\": 2}", &options).err(), Some(ParseError { kind: ParseErrorKind::KeyTooLong, offset: 10 }));
+		assert_eq!(Json::parse_with(b"[{\"a\": {\"abc\": 2}}]", &options).err(), Some(ParseError { kind: ParseErrorKind::KeyTooLong, offset: 8 }));
+		assert!(Json::parse_with(b"[\"abc\"]", &options).is_ok());
+	}
+
+	#[test]
+	fn coerce_numeric_strings()
+	{
+		let mut value = json(b"[\"42\", \"1e3\", \"-0.5\", \"x\", \"1 \", \"\", {\"a\": \"7\"}]");
+		value.coerce_numeric_strings();
+		assert_eq!(value.to_string(), "[42,1e3,-0.5,\"x\",\"1 \",\"\",{\"a\":7}]");
+	}
+
+	#[test]
+	fn invalid_surrogates()
+	{
+		let error = |bytes: &[u8]| Json::parse_with(bytes, &ParseOptions::default()).err();
+		let invalid_surrogate = |offset| Some(ParseError { kind: ParseErrorKind::InvalidSurrogate, offset });
+		assert_eq!(error(b"\"\\ud800\""), invalid_surrogate(1));
+		assert_eq!(error(b"\"\\ude00\""), invalid_surrogate(1));
+		assert_eq!(error(b"\"\\ud800\\u0041\""), invalid_surrogate(1));
+		assert_eq!(error(b"[\"ab\", \"c\\uDBFF\"]"), invalid_surrogate(9));
+		assert!(error(b"\"\\ud83d\\ude00\"").is_none());
+	}
+
+	#[test]
+	fn subsets()
+	{
+		let response = json(b"{\"a\": 1, \"b\": {\"c\": [1, {\"d\": 2, \"e\": 3}]}, \"f\": null}");
+		assert!(json(b"{\"b\": {\"c\": [1, {\"d\": 2}]}}").is_subset_of(&response));
+		assert!(json(b"{}").is_subset_of(&response));
+		assert!(!json(b"{\"b\": {\"c\": [1]}}").is_subset_of(&response));
+		assert!(!json(b"{\"g\": null}").is_subset_of(&response));
+		assert!(!json(b"{\"a\": 2}").is_subset_of(&response));
+		assert!(!json(b"[]").is_subset_of(&json(b"{}")));
+	}
+
+	#[test]
+	fn strip_nulls()
+	{
+		let mut value = json(b"{\"a\": [\"x\", null, {\"b\": null}], \"c\": null, \"d\": {\"e\": null}}");
+		value.strip_nulls();
+		assert_eq!(value.to_string(), "{\"a\":[\"x\",null,{}],\"d\":{}}");
+		let mut value = json(b"{\"a\": [null, 1], \"b\": null, \"c\": {\"d\": null}}");
+		value.strip_nulls_with(true);
+		assert_eq!(value.to_string(), "{\"a\":[1],\"c\":{}}");
+	}
+
+	#[test]
+	fn type_histogram()
+	{
+		let histogram = json(b"{\"a\": [1, 2, \"x\"], \"b\": null}").type_histogram();
+		let expected = [("array", 1), ("null", 1), ("number", 2), ("object", 1), ("string", 1)];
+		assert_eq!(histogram.into_iter().collect::<Vec<_>>(), expected);
+	}
+
+	#[test]
+	fn normalize_negative_zero()
+	{
+		let parse = |bytes: &[u8], options: &ParseOptions| Json::parse_with(bytes, options).unwrap();
+		let options = ParseOptions { normalize_negative_zero: true, ..ParseOptions::default() };
+		let value = parse(b"[-0, -0.0, -0e5, 0, -1]", &options);
+		assert_eq!(value.to_string(), "[0,0,0,0,-1]");
+		match value.into_array() {
+			Some(array) => assert!(array.iter().all(|element| !matches!(element, Json::Number(number) if *number == 0.0 && number.is_sign_negative()))),
+			None => panic!("expected an Array"),
+		}
+		assert_eq!(parse(b"[-0]", &ParseOptions::default()).to_string(), "[-0]");
+	}
+
+	#[test]
+	fn deep_merge_by_index()
+	{
+		let mut base = json(b"[{\"a\": 1}, 2, 3]");
+		base.deep_merge_with(json(b"[{\"b\": 2}, 4]"), ArrayMergePolicy::ByIndex);
+		assert_eq!(base.to_string(), "[{\"a\":1,\"b\":2},4,3]");
+		let mut base = json(b"[1]");
+		base.deep_merge_with(json(b"[2, 3]"), ArrayMergePolicy::ByIndex);
+		assert_eq!(base.to_string(), "[2,3]");
+		let mut base = json(b"{\"a\": [1, [2, 3]]}");
+		base.deep_merge_with(json(b"{\"a\": [null, [4]]}"), ArrayMergePolicy::ByIndex);
+		assert_eq!(base.to_string(), "{\"a\":[null,[4,3]]}");
+		let mut base = json(b"[1, 2]");
+		base.deep_merge_with(json(b"[3]"), ArrayMergePolicy::Replace);
+		assert_eq!(base.to_string(), "[3]");
+	}
+
+	#[test]
+	fn is_one_of()
+	{
+		let allowed = [json(b"1"), json(b"\"a\""), json(b"{\"b\": [null]}")];
+		assert!(json(b"1.0").is_one_of(&allowed));
+		assert!(json(b"{\"b\": [null]}").is_one_of(&allowed));
+		assert!(!json(b"\"1\"").is_one_of(&allowed));
+		assert!(!json(b"{\"b\": []}").is_one_of(&allowed));
+		assert!(!json(b"1").is_one_of(&[]));
+	}
+
+	#[test]
+	fn nullify_non_finite()
+	{
+		let mut value = Json::Array(vec![Json::Number(1.0), Json::Number(f64::NAN), Json::Array(vec![Json::Number(f64::INFINITY)])]);
+		value.nullify_non_finite();
+		assert!(value.all_finite());
+		assert_eq!(value.to_string(), "[1,null,[null]]");
+		let mut value = Json::Number(f64::NEG_INFINITY);
+		value.nullify_non_finite();
+		assert!(value == Json::Null);
+	}
+
+	#[test]
+	fn common_structure()
+	{
+		let a = json(b"{\"a\": 1, \"b\": {\"c\": 2, \"d\": 3}, \"e\": [1]}");
+		let b = json(b"{\"a\": 1, \"b\": {\"c\": 2, \"d\": 4}, \"e\": [2]}");
+		assert_eq!(a.common(&b).to_string(), "{\"a\":1,\"b\":{\"c\":2}}");
+		assert!(json(b"1").common(&json(b"2")) == Json::Null);
+		assert!(json(b"[1]").common(&json(b"[1]")) == json(b"[1]"));
+	}
+
+	#[test]
+	fn max_tokens()
+	{
+		let options = ParseOptions { max_tokens: Some(4), ..ParseOptions::default() };
+		assert_eq!(Json::parse_with(b"[1,2]", &options).err(), Some(ParseError { kind: ParseErrorKind::TooManyTokens, offset: 4 }));
+		assert!(Json::parse_with(b"[12]", &options).is_ok());
+		assert!(Json::parse_with(b"{\"a\":1}", &ParseOptions { max_tokens: Some(5), ..options }).is_ok());
+	}
+
+	#[test]
+	fn into_array_coerce()
+	{
+		assert_eq!(json(b"1").into_array_coerce().to_string(), "[1]");
+		assert_eq!(json(b"{\"a\": 1}").into_array_coerce().to_string(), "[{\"a\":1}]");
+		assert_eq!(json(b"[1]").into_array_coerce().to_string(), "[1]");
+		assert_eq!(json(b"[]").into_array_coerce().to_string(), "[]");
+	}
+
+	#[test]
+	fn parse_number()
+	{
+		assert_eq!(Json::parse_number(b" -1.5e2 "), Some(-150.0));
+		assert_eq!(Json::parse_number(b"0"), Some(0.0));
+		assert_eq!(Json::parse_number(b"1 2"), None);
+		assert_eq!(Json::parse_number(b"\"1\""), None);
+		assert_eq!(Json::parse_number(b"01"), None);
+		assert_eq!(Json::parse_number(b""), None);
+	}
+
+	#[test]
+	fn parse_string()
+	{
+		assert_eq!(Json::parse_string(b" \"a\\nb\" "), Some("a\nb".to_string()));
+		assert_eq!(Json::parse_string(b"\"\\u00e9\""), Some("\u{e9}".to_string()));
+		assert_eq!(Json::parse_string(b"\"a\" \"b\""), None);
+		assert_eq!(Json::parse_string(b"\"a"), None);
+		assert_eq!(Json::parse_string(b"1"), None);
+	}
+
+	#[test]
+	fn get_presence()
+	{
+		let value = json(b"{\"a\": null, \"b\": {\"c\": [1]}}");
+		assert!(matches!(value.get_presence("a"), Presence::Null));
+		assert!(matches!(value.get_presence("b"), Presence::Value(Json::Object(_))));
+		assert!(matches!(value.get_presence("z"), Presence::Absent));
+		assert!(matches!(Json::Null.get_presence("a"), Presence::Absent));
+	}
+
+	#[test]
+	fn surrogate_pairs_and_bmp_escapes()
+	{
+		assert!(json(b"\"\\u0041\\u00e9\\u4e2d\\uffff\"") == Json::String("A\u{e9}\u{4e2d}\u{ffff}".to_string()));
+		assert!(json(b"\"\\ud83d\\ude00x\\uD834\\uDD1E\"") == Json::String("\u{1f600}x\u{1d11e}".to_string()));
+		assert!(json(b"\"\\u4e2d\\ud83d\\ude00\\u4e2d\"") == Json::String("\u{4e2d}\u{1f600}\u{4e2d}".to_string()));
+	}
+
+	#[test]
+	fn entries_sorted_by()
+	{
+		let value = json(b"{\"b\": 1, \"a\": 3, \"c\": 2}");
+		let by_value = value.entries_sorted_by(|a, b| a.1.to_string().cmp(&b.1.to_string())).unwrap();
+		assert_eq!(by_value.iter().map(|(key, _)| *key).collect::<Vec<_>>(), ["b", "c", "a"]);
+		let by_key = value.entries_sorted_by(|a, b| b.0.cmp(a.0)).unwrap();
+		assert_eq!(by_key.iter().map(|(key, _)| *key).collect::<Vec<_>>(), ["c", "b", "a"]);
+		assert!(json(b"[]").entries_sorted_by(|a, b| a.0.cmp(b.0)).is_none());
+	}
+
+	#[test]
+	fn fill_defaults()
+	{
+		let mut value = json(b"{\"a\": null, \"b\": {\"c\": 1}}");
+		value.fill_defaults(&json(b"{\"a\": 1, \"b\": {\"c\": 2, \"d\": 3}, \"e\": [4]}"));
+		assert_eq!(value.to_string(), "{\"a\":null,\"b\":{\"c\":1,\"d\":3},\"e\":[4]}");
+	}
+
+	#[test]
+	fn find_strings()
+	{
+		let value = json(b"{\"a\": [\"ax\", null, \"b\"], \"ay\": {\"c\": \"az\"}}");
+		assert_eq!(value.find_strings(|string| string.starts_with('a')), ["ax", "az"]);
+		assert!(value.find_strings(|string| string.is_empty()).is_empty());
+		assert_eq!(json(b"\"a\"").find_strings(|_| true), ["a"]);
+	}
+
+	#[test]
+	fn replace_where()
+	{
+		let mut value = json(b"{\"a\": [1, \"x\", [2]], \"b\": 3}");
+		value.replace_where(Json::is_number, |number| Json::String(number.to_string()));
+		assert_eq!(value.to_string(), "{\"a\":[\"1\",\"x\",[\"2\"]],\"b\":\"3\"}");
+		value.replace_where(Json::is_array, |_| Json::Null);
+		assert_eq!(value.to_string(), "{\"a\":null,\"b\":\"3\"}");
+		value.replace_where(Json::is_object, |_| Json::Boolean(true));
+		assert!(value == Json::Boolean(true));
+	}
+
+	#[test]
+	fn unwrap_singletons()
+	{
+		let mut value = json(b"{\"a\": [[1]], \"b\": [1, [2]], \"c\": {\"d\": [3]}}");
+		value.unwrap_singletons();
+		assert_eq!(value.to_string(), "{\"a\":1,\"b\":[1,2],\"c\":{\"d\":3}}");
+		value.unwrap_singletons_with(true);
+		assert_eq!(value.to_string(), "{\"a\":1,\"b\":[1,2],\"c\":3}");
+		let mut value = json(b"{\"a\": {\"b\": [{\"c\": 1}]}}");
+		value.unwrap_singletons_with(true);
+		assert_eq!(value.to_string(), "1");
+	}
+
+	#[test]
+	fn partition_array()
+	{
+		let (numbers, rest) = json(b"[1, \"a\", 2, null]").partition_array(Json::is_number).unwrap();
+		assert_eq!((numbers.to_string(), rest.to_string()), ("[1,2]".to_string(), "[\"a\",null]".to_string()));
+		let (all, none) = json(b"[]").partition_array(Json::is_number).unwrap();
+		assert_eq!((all.to_string(), none.to_string()), ("[]".to_string(), "[]".to_string()));
+		assert!(json(b"{}").partition_array(Json::is_number).is_none());
+	}
+
+	#[test]
+	fn is_flat()
+	{
+		assert!(json(b"[1, \"a\", null]").is_flat());
+		assert!(json(b"{\"a\": 1, \"b\": true}").is_flat());
+		assert!(!json(b"{\"a\": []}").is_flat());
+		assert!(!json(b"[[1]]").is_flat());
+		assert!(json(b"1").is_flat());
+	}
+
+	#[test]
+	fn dotted_keys()
+	{
+		let value = json(b"{\"a\": {\"b\": [1, {\"c\": null}]}, \"d\": [], \"e\": {}}");
+		let dotted = value.to_dotted(".");
+		let keys = dotted.keys().map(String::as_str).collect::<Vec<_>>();
+		assert_eq!(keys, ["a.b.0", "a.b.1.c", "d", "e"]);
+		assert!(Json::from_dotted(dotted, ".") == value);
+		assert_eq!(json(b"1").to_dotted("/").keys().collect::<Vec<_>>(), [""]);
+		assert!(Json::from_dotted(json(b"1").to_dotted("."), ".") == json(b"1"));
+		let overlapping = BTreeMap::from([("a".to_string(), Json::Null), ("a.b".to_string(), Json::Boolean(true))]);
+		assert_eq!(Json::from_dotted(overlapping, ".").to_string(), "{\"a\":{\"b\":true}}");
+	}
+
+	#[test]
+	fn max_number_abs()
+	{
+		let options = ParseOptions { max_number_abs: Some(10.0), ..ParseOptions::default() };
+		assert!(Json::parse_with(b"[10, -10, 0.5]", &options).is_ok());
+		assert_eq!(Json::parse_with(b"[10, -11]", &options).err(), Some(ParseError { kind: ParseErrorKind::NumberTooLarge, offset: 5 }));
+		let options = ParseOptions { allow_non_finite: true, ..options };
+		assert_eq!(Json::parse_with(b"NaN", &options).err(), Some(ParseError { kind: ParseErrorKind::NumberTooLarge, offset: 0 }));
+		assert_eq!(Json::parse_with(b"[1, -Infinity]", &options).err(), Some(ParseError { kind: ParseErrorKind::NumberTooLarge, offset: 4 }));
+		assert!(Json::parse_with(b"NaN", &ParseOptions { max_number_abs: None, ..options }).is_ok());
+	}
+
+	#[test]
+	fn is_homogeneous_array()
+	{
+		assert_eq!(json(b"[1, 2.5]").is_homogeneous_array(), Some(true));
+		assert_eq!(json(b"[{\"a\": 1}, {}]").is_homogeneous_array(), Some(true));
+		assert_eq!(json(b"[1, \"2\"]").is_homogeneous_array(), Some(false));
+		assert_eq!(json(b"[[], {}]").is_homogeneous_array(), Some(false));
+		assert_eq!(json(b"[]").is_homogeneous_array(), Some(true));
+		assert_eq!(json(b"{}").is_homogeneous_array(), None);
+	}
+
+	#[test]
+	fn object_from_columns()
+	{
+		let keys = ["a".to_string(), "b".to_string()];
+		assert!(Json::object_from_columns(&keys, vec![Json::Null, Json::Number(1.0)]).is_ok_and(|object| object == json(b"{\"a\": null, \"b\": 1}")));
+		assert!(Json::object_from_columns(&keys, vec![Json::Null]).is_err());
+		assert!(Json::object_from_columns(&["a".to_string(), "a".to_string()], vec![Json::Null, Json::Null]).is_err());
+		assert!(Json::object_from_columns(&[], Vec::new()).is_ok_and(|object| object == json(b"{}")));
+	}
+
+	#[test]
+	fn eq_to_depth()
+	{
+		assert!(json(b"{\"a\": [1, [2]]}").eq_to_depth(&json(b"{\"a\": [1, [3]]}"), 2));
+		assert!(!json(b"{\"a\": [1, [2]]}").eq_to_depth(&json(b"{\"a\": [1, [3]]}"), 3));
+		assert!(!json(b"{\"a\": [1]}").eq_to_depth(&json(b"{\"a\": [1, 2]}"), 1));
+		assert!(json(b"{\"a\": [1]}").eq_to_depth(&json(b"{\"a\": [1, 2]}"), 0));
+		assert!(!json(b"[]").eq_to_depth(&json(b"{}"), 0));
+		assert!(json(b"1").eq_to_depth(&json(b"1.0"), 0));
+	}
+
+	#[test]
+	fn control_character_errors()
+	{
+		assert_eq!(Json::parse_detailed(b"[\"ab\x01\"]").err(), Some(ParseError { kind: ParseErrorKind::ControlCharacter(0x01), offset: 4 }));
+		assert_eq!(Json::parse_detailed(b"{\"a\": \"\\n\x1f\"}").err(), Some(ParseError { kind: ParseErrorKind::ControlCharacter(0x1f), offset: 9 }));
+		assert_eq!(Json::parse_detailed(b"{\"a\nb\": 1}").err(), Some(ParseError { kind: ParseErrorKind::ControlCharacter(b'\n'), offset: 3 }));
+		assert_eq!(format!("{}", ParseError { kind: ParseErrorKind::ControlCharacter(0x1f), offset: 3 }), "control character 0x1f at byte 3");
+	}
+
+	#[test]
+	fn project()
+	{
+		let value = json(b"{\"id\": 5, \"extra\": 1, \"owner\": {\"name\": \"x\", \"age\": 3}, \"items\": [{\"a\": 1, \"b\": 2}]}");
+		let projection = value.project(&json(b"{\"id\": null, \"owner\": {\"name\": null}, \"items\": [{\"b\": null}], \"missing\": 0}"));
+		assert_eq!(projection.to_string(), "{\"id\":5,\"items\":[{\"b\":2}],\"owner\":{\"name\":\"x\"}}");
+		assert_eq!(value.project(&json(b"{}")).to_string(), "{}");
+		assert_eq!(json(b"[1]").project(&json(b"{\"a\": null}")).to_string(), "[1]");
+	}
+
+	#[test]
+	fn error_kinds_and_offsets()
+	{
+		let error = |bytes: &[u8]| Json::parse_detailed(bytes).err();
+		let expected = |kind, offset| Some(ParseError { kind, offset });
+		assert_eq!(error(b""), expected(ParseErrorKind::UnexpectedEnd, 0));
+		assert_eq!(error(b"[1, tru]"), expected(ParseErrorKind::InvalidToken, 4));
+		assert_eq!(error(b"[01]"), expected(ParseErrorKind::UnexpectedToken, 2));
+		assert_eq!(error(b"[1.]"), expected(ParseErrorKind::InvalidToken, 1));
+		assert_eq!(error(b"[+1]"), expected(ParseErrorKind::InvalidToken, 1));
+		assert_eq!(error(b"['a']"), expected(ParseErrorKind::InvalidToken, 1));
+		assert_eq!(error(b"{a: 1}"), expected(ParseErrorKind::InvalidToken, 1));
+		assert_eq!(error(b"[1 2]"), expected(ParseErrorKind::UnexpectedToken, 3));
+		assert_eq!(error(b"{\"a\" 1}"), expected(ParseErrorKind::UnexpectedToken, 5));
+		assert_eq!(error(b"{\"a\": 1, \"a\": 2}"), expected(ParseErrorKind::DuplicateKey, 9));
+		assert_eq!(error(b"\"\\x\""), expected(ParseErrorKind::InvalidToken, 0));
+	}
+}
@@ -0,0 +1,49 @@
+use unicode_normalization::is_nfc;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::Json;
+
+
+impl Json
+{
+	/// Normalize every String value and Object key to Unicode NFC, so text
+	/// which is composed differently is equal, like `é` as one or two
+	/// characters. If keys become the same, then the last one wins.
+	pub fn normalize_unicode(&mut self)
+	{
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			match value {
+				Json::Array(array) => stack.extend(array.iter_mut()),
+				Json::Object(object) => {
+					if !object.keys().all(|key| is_nfc(key)) {
+						*object = core::mem::take(object).into_iter()
+							.map(|(key, member)| (key.nfc().collect(), member))
+							.collect();
+					}
+					stack.extend(object.values_mut());
+				},
+				Json::String(string) if !is_nfc(string) => *string = string.nfc().collect(),
+				_ => (),
+			}
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	#[test]
+	fn normalize_strings_and_keys()
+	{
+		let mut value = Json::parse("{\"e\u{301}\": [\"e\u{301}\", \"\u{e9}\"], \"\u{e9}\": 1, \"a\": \"A\u{30a}\"}".as_bytes()).unwrap();
+		value.normalize_unicode();
+		assert_eq!(value.to_string(), "{\"a\":\"\u{c5}\",\"\u{e9}\":1}");
+		let mut value = Json::parse("[\"e\u{301}\", {\"b\": \"\u{e9}\"}]".as_bytes()).unwrap();
+		value.normalize_unicode();
+		assert!(value == Json::parse("[\"\u{e9}\", {\"b\": \"\u{e9}\"}]".as_bytes()).unwrap());
+	}
+}
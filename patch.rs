@@ -0,0 +1,364 @@
+use std::collections::BTreeMap;
+
+use crate::pointer::escape_token;
+use crate::pointer::find;
+use crate::pointer::find_parent;
+use crate::pointer::parse_index;
+use crate::pointer::parse_pointer;
+use crate::Json;
+use crate::PatchError;
+use crate::PointerOptions;
+
+
+/// A change between two values for display, at the JSON Pointer of the value
+#[derive(Clone, PartialEq)]
+pub struct DiffEntry
+{
+	pub pointer: String,
+	pub change: DiffChange,
+}
+
+
+/// The kind of change of a value with the values before and after
+#[derive(Clone, PartialEq)]
+pub enum DiffChange
+{
+	Added(Json),
+	Removed(Json),
+	Changed { from: Json, to: Json },
+}
+
+
+impl Json
+{
+	/// Get an RFC 6902 patch of `add`, `remove`, and `replace` operations which
+	/// transforms the old value into the new value. Array elements are
+	/// compared by index, so an insertion replaces every later element.
+	pub fn diff(old: &Json, new: &Json) -> Json
+	{
+		let mut operations = Vec::<Json>::new();
+		let mut stack = vec![(old, new, String::new())];
+
+		while let Some((old, new, path)) = stack.pop() {
+			match (old, new) {
+				(Json::Array(old_array), Json::Array(new_array)) => {
+					let common_len = old_array.len().min(new_array.len());
+					// Remove from the end so the indices before stay the same
+					for i in (common_len..old_array.len()).rev() {
+						operations.push(operation("remove", format!("{}/{}", path, i), None));
+					}
+					for (i, element) in new_array.iter().enumerate().skip(common_len) {
+						operations.push(operation("add", format!("{}/{}", path, i), Some(element)));
+					}
+					for i in (0..common_len).rev() {
+						stack.push((&old_array[i], &new_array[i], format!("{}/{}", path, i)));
+					}
+				},
+				(Json::Object(old_object), Json::Object(new_object)) => {
+					for key in old_object.keys().filter(|key| !new_object.contains_key(*key)) {
+						operations.push(operation("remove", format!("{}/{}", path, escape_token(key)), None));
+					}
+					for (key, new_member) in new_object.iter().rev() {
+						let member_path = format!("{}/{}", path, escape_token(key));
+						match old_object.get(key) {
+							Some(old_member) => stack.push((old_member, new_member, member_path)),
+							None => operations.push(operation("add", member_path, Some(new_member))),
+						}
+					}
+				},
+				_ if old != new => operations.push(operation("replace", path, Some(new))),
+				_ => (),
+			}
+		}
+
+		return Json::Array(operations);
+	}
+
+	/// Get the changes from the old value to the new value in document order,
+	/// where Objects and Arrays are compared by member and by index, and any
+	/// other difference is a change of the whole value
+	pub fn diff_summary(old: &Json, new: &Json) -> Vec<DiffEntry>
+	{
+		/// A pair of values to compare, or a change found in order
+		enum Step<'a>
+		{
+			Compare(&'a Json, &'a Json, String),
+			Entry(String, DiffChange),
+		}
+
+		let mut entries = Vec::<DiffEntry>::new();
+		let mut stack = vec![Step::Compare(old, new, String::new())];
+
+		while let Some(step) = stack.pop() {
+			let (old, new, pointer) = match step {
+				Step::Compare(old, new, pointer) => (old, new, pointer),
+				Step::Entry(pointer, change) => {
+					entries.push(DiffEntry { pointer, change });
+					continue;
+				},
+			};
+			// Find the steps in order, then push them in reverse
+			let mut steps = Vec::<Step>::new();
+			match (old, new) {
+				(Json::Array(old_array), Json::Array(new_array)) => {
+					for i in 0..old_array.len().max(new_array.len()) {
+						let element_pointer = format!("{}/{}", pointer, i);
+						steps.push(match (old_array.get(i), new_array.get(i)) {
+							(Some(old_element), Some(new_element)) => Step::Compare(old_element, new_element, element_pointer),
+							(Some(old_element), None) => Step::Entry(element_pointer, DiffChange::Removed(old_element.clone())),
+							(None, Some(new_element)) => Step::Entry(element_pointer, DiffChange::Added(new_element.clone())),
+							(None, None) => unreachable!(),
+						});
+					}
+				},
+				(Json::Object(old_object), Json::Object(new_object)) => {
+					let mut keys = old_object.keys().chain(new_object.keys()).collect::<Vec<&String>>();
+					keys.sort();
+					keys.dedup();
+					for key in keys {
+						let member_pointer = format!("{}/{}", pointer, escape_token(key));
+						steps.push(match (old_object.get(key), new_object.get(key)) {
+							(Some(old_member), Some(new_member)) => Step::Compare(old_member, new_member, member_pointer),
+							(Some(old_member), None) => Step::Entry(member_pointer, DiffChange::Removed(old_member.clone())),
+							(None, Some(new_member)) => Step::Entry(member_pointer, DiffChange::Added(new_member.clone())),
+							(None, None) => unreachable!(),
+						});
+					}
+				},
+				_ if old != new => steps.push(Step::Entry(pointer, DiffChange::Changed { from: old.clone(), to: new.clone() })),
+				_ => (),
+			}
+			stack.extend(steps.into_iter().rev());
+		}
+
+		return entries;
+	}
+
+	/// Apply the RFC 6902 patch with the operations `add`, `remove`,
+	/// `replace`, `move`, `copy`, and `test`. If any operation fails, then the
+	/// value isn't changed.
+	pub fn apply_patch(&mut self, patch: &Json) -> Result<(), PatchError>
+	{
+		let operations = match patch {
+			Json::Array(operations) => operations,
+			_ => return Err(PatchError::InvalidPatch),
+		};
+
+		let mut patched = self.clone();
+		for operation in operations {
+			let member = |key: &str| match operation {
+				Json::Object(operation) => operation.get(key),
+				_ => None,
+			};
+			let string_member = |key: &str| match member(key) {
+				Some(Json::String(string)) => Ok(string.as_str()),
+				_ => Err(PatchError::InvalidPatch),
+			};
+			let value_member = || member("value").ok_or(PatchError::InvalidPatch);
+
+			let path = string_member("path")?;
+			match string_member("op")? {
+				"add" => add(&mut patched, path, value_member()?.clone())?,
+				"remove" => {
+					let options = PointerOptions { require_existing: true, ..PointerOptions::default() };
+					patched.remove_pointer_with(path, &options)?;
+				},
+				"replace" => replace(&mut patched, path, value_member()?.clone())?,
+				"move" => {
+					let from = string_member("from")?;
+					if path.strip_prefix(from).is_some_and(|rest| rest.starts_with('/')) {
+						return Err(PatchError::InvalidPatch);
+					}
+					let options = PointerOptions { require_existing: true, ..PointerOptions::default() };
+					if let Some(value) = patched.remove_pointer_with(from, &options)? {
+						add(&mut patched, path, value)?;
+					}
+				},
+				"copy" => {
+					let from = string_member("from")?;
+					let value = find(&patched, &parse_pointer(from)?).ok_or(PatchError::NotFound)?.clone();
+					add(&mut patched, path, value)?;
+				},
+				"test" => {
+					let value = find(&patched, &parse_pointer(path)?).ok_or(PatchError::NotFound)?;
+					if value != value_member()? {
+						return Err(PatchError::TestFailed);
+					}
+				},
+				_ => return Err(PatchError::InvalidPatch),
+			}
+		}
+
+		*self = patched;
+		return Ok(());
+	}
+}
+
+
+/// Create a patch operation with the path and the value
+fn operation(op: &str, path: String, value: Option<&Json>) -> Json
+{
+	let mut operation = BTreeMap::<String, Json>::new();
+	operation.insert("op".to_string(), Json::String(op.to_string()));
+	operation.insert("path".to_string(), Json::String(path));
+	if let Some(value) = value {
+		operation.insert("value".to_string(), value.clone());
+	}
+	return Json::Object(operation);
+}
+
+
+/// Add the value at the path, where an Array index inserts before the element
+fn add(root: &mut Json, path: &str, value: Json) -> Result<(), PatchError>
+{
+	let tokens = parse_pointer(path)?;
+	let (last, parents) = match tokens.split_last() {
+		Some(split) => split,
+		None => {
+			*root = value;
+			return Ok(());
+		},
+	};
+	match find_parent(root, parents, last, false)? {
+		Json::Array(array) => match (parse_index(last), last.as_str()) {
+			(Some(index), _) if index <= array.len() => array.insert(index, value),
+			(_, "-") => array.push(value),
+			_ => return Err(PatchError::InvalidIndex),
+		},
+		Json::Object(object) => {
+			object.insert(last.clone(), value);
+		},
+		_ => return Err(PatchError::NotContainer),
+	}
+	return Ok(());
+}
+
+
+/// Replace the value at the path, which must exist
+fn replace(root: &mut Json, path: &str, value: Json) -> Result<(), PatchError>
+{
+	let tokens = parse_pointer(path)?;
+	let (last, parents) = match tokens.split_last() {
+		Some(split) => split,
+		None => {
+			*root = value;
+			return Ok(());
+		},
+	};
+	let target = match find_parent(root, parents, last, false)? {
+		Json::Array(array) => parse_index(last).and_then(|index| array.get_mut(index)),
+		Json::Object(object) => object.get_mut(last),
+		_ => return Err(PatchError::NotContainer),
+	};
+	return match target {
+		Some(target) => {
+			*target = value;
+			Ok(())
+		},
+		None => Err(PatchError::NotFound),
+	};
+}
+
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	/// Parse the JSON data which must be valid
+	fn json(bytes: &[u8]) -> Json
+	{
+		return Json::parse(bytes).unwrap();
+	}
+
+	#[test]
+	fn diff_operations()
+	{
+		let old = json(b"{\"a\": [1, 2, 3], \"b\": 1, \"c/d\": true}");
+		let new = json(b"{\"a\": [1, 4], \"b\": 1, \"e\": null}");
+		assert_eq!(
+			Json::diff(&old, &new).to_string(),
+			"[{\"op\":\"remove\",\"path\":\"/c~1d\"},{\"op\":\"add\",\"path\":\"/e\",\"value\":null},\
+			{\"op\":\"remove\",\"path\":\"/a/2\"},{\"op\":\"replace\",\"path\":\"/a/1\",\"value\":4}]"
+		);
+		assert_eq!(Json::diff(&old, &old).to_string(), "[]");
+		assert_eq!(Json::diff(&old, &Json::Null).to_string(), "[{\"op\":\"replace\",\"path\":\"\",\"value\":null}]");
+	}
+
+	#[test]
+	fn diff_then_apply_patch()
+	{
+		let values = [
+			json(b"{\"a\": [1, 2, 3], \"b\": {\"c\": \"d\"}, \"~\": 1}"),
+			json(b"{\"a\": [1], \"b\": {\"c\": \"e\", \"f\": [[]]}, \"/\": 2}"),
+			json(b"{\"a\": [0, 1, 2, 3, 4], \"b\": []}"),
+			json(b"[{\"a\": 1}, 2]"),
+			json(b"null"),
+			json(b"{}"),
+		];
+		for old in &values {
+			for new in &values {
+				let mut patched = old.clone();
+				assert!(patched.apply_patch(&Json::diff(old, new)).is_ok());
+				assert!(patched == *new);
+			}
+		}
+	}
+
+	#[test]
+	fn apply_patch_operations()
+	{
+		let mut value = json(b"{\"a\": [1, 2], \"b\": {\"c\": 3}}");
+		let patch = json(b"[
+			{\"op\": \"add\", \"path\": \"/a/1\", \"value\": 9},
+			{\"op\": \"add\", \"path\": \"/a/-\", \"value\": 10},
+			{\"op\": \"remove\", \"path\": \"/a/0\"},
+			{\"op\": \"replace\", \"path\": \"/b/c\", \"value\": [4]},
+			{\"op\": \"copy\", \"from\": \"/b/c\", \"path\": \"/d\"},
+			{\"op\": \"move\", \"from\": \"/b\", \"path\": \"/e\"},
+			{\"op\": \"test\", \"path\": \"/d\", \"value\": [4]}
+		]");
+		assert!(value.apply_patch(&patch).is_ok());
+		assert_eq!(value.to_string(), "{\"a\":[9,2,10],\"d\":[4],\"e\":{\"c\":[4]}}");
+	}
+
+	#[test]
+	fn apply_patch_errors_leave_value_unchanged()
+	{
+		let original = json(b"{\"a\": [1], \"b\": {}}");
+		let patches: [(&[u8], PatchError); 10] = [
+			(b"{}", PatchError::InvalidPatch),
+			(b"[{\"op\": \"add\", \"path\": \"/c\"}]", PatchError::InvalidPatch),
+			(b"[{\"op\": \"nope\", \"path\": \"/c\"}]", PatchError::InvalidPatch),
+			(b"[{\"op\": \"remove\"}]", PatchError::InvalidPatch),
+			(b"[{\"op\": \"remove\", \"path\": \"/c\"}]", PatchError::NotFound),
+			(b"[{\"op\": \"replace\", \"path\": \"/c\", \"value\": 1}]", PatchError::NotFound),
+			(b"[{\"op\": \"add\", \"path\": \"/a/5\", \"value\": 1}]", PatchError::InvalidIndex),
+			(b"[{\"op\": \"add\", \"path\": \"/a/0/x\", \"value\": 1}]", PatchError::NotContainer),
+			(b"[{\"op\": \"move\", \"from\": \"/b\", \"path\": \"/b/c\"}]", PatchError::InvalidPatch),
+			(b"[{\"op\": \"add\", \"path\": \"/c\", \"value\": 1}, {\"op\": \"test\", \"path\": \"/c\", \"value\": 2}]", PatchError::TestFailed),
+		];
+		for (patch, error) in patches {
+			let mut value = original.clone();
+			assert_eq!(value.apply_patch(&json(patch)), Err(error));
+			assert!(value == original);
+		}
+	}
+
+	#[test]
+	fn diff_summary()
+	{
+		let old = json(b"{\"a\": [1, 2], \"b\": 1, \"c\": {\"d\": true}}");
+		let new = json(b"{\"a\": [1, 3, 4], \"c\": {\"d\": false}, \"e\": null}");
+		let entries = Json::diff_summary(&old, &new);
+		let expected = [
+			DiffEntry { pointer: "/a/1".to_string(), change: DiffChange::Changed { from: Json::Number(2.0), to: Json::Number(3.0) } },
+			DiffEntry { pointer: "/a/2".to_string(), change: DiffChange::Added(Json::Number(4.0)) },
+			DiffEntry { pointer: "/b".to_string(), change: DiffChange::Removed(Json::Number(1.0)) },
+			DiffEntry { pointer: "/c/d".to_string(), change: DiffChange::Changed { from: Json::Boolean(true), to: Json::Boolean(false) } },
+			DiffEntry { pointer: "/e".to_string(), change: DiffChange::Added(Json::Null) },
+		];
+		assert!(entries == expected);
+		assert!(Json::diff_summary(&old, &old).is_empty());
+	}
+}
@@ -0,0 +1,395 @@
+use core::fmt;
+use std::collections::btree_map::Entry;
+
+use crate::Json;
+
+
+/// Options for editing values by JSON Pointer
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PointerOptions
+{
+	/// Create the missing containers before the last reference token, which
+	/// are Arrays for `-` and Objects otherwise
+	pub create_missing: bool,
+	/// Fail to remove a value which doesn't exist, instead of getting `None`
+	pub require_existing: bool,
+}
+
+
+/// The reason a JSON Pointer or patch couldn't be applied
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PatchError
+{
+	/// The pointer isn't empty and doesn't begin with `/`, or it has a `~`
+	/// which isn't `~0` or `~1`
+	InvalidPointer,
+	/// The reference token isn't `-` or an index of the Array
+	InvalidIndex,
+	/// The value, or the value before the last reference token, doesn't exist
+	NotFound,
+	/// The value before the last reference token isn't an Array or Object
+	NotContainer,
+	/// The patch isn't an Array of operations with the members they need
+	InvalidPatch,
+	/// The value of a `test` operation isn't equal to the value at the path
+	TestFailed,
+}
+
+
+impl fmt::Display for PatchError
+{
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result
+	{
+		let reason = match self {
+			PatchError::InvalidPointer => "invalid pointer",
+			PatchError::InvalidIndex => "invalid index",
+			PatchError::NotFound => "not found",
+			PatchError::NotContainer => "not a container",
+			PatchError::InvalidPatch => "invalid patch",
+			PatchError::TestFailed => "test failed",
+		};
+		return formatter.write_str(reason);
+	}
+}
+
+
+impl std::error::Error for PatchError
+{
+}
+
+
+impl Json
+{
+	/// Set the value at the JSON Pointer and get the value it replaced, where
+	/// the last reference token may be `-` to append to an Array
+	pub fn set_pointer(&mut self, pointer: &str, value: Json) -> Result<Option<Json>, PatchError>
+	{
+		return self.set_pointer_with(pointer, value, &PointerOptions::default());
+	}
+
+	/// Set the value at the JSON Pointer with the options and get the value it
+	/// replaced, where the last reference token may be `-` to append to an
+	/// Array
+	pub fn set_pointer_with(&mut self, pointer: &str, value: Json, options: &PointerOptions) -> Result<Option<Json>, PatchError>
+	{
+		let tokens = parse_pointer(pointer)?;
+		let (last, parents) = match tokens.split_last() {
+			Some(split) => split,
+			None => return Ok(Some(core::mem::replace(self, value))),
+		};
+
+		let parent = find_parent(self, parents, last, options.create_missing)?;
+		return match parent {
+			Json::Array(array) => match (parse_index(last), last.as_str()) {
+				(Some(index), _) if index < array.len() => Ok(Some(core::mem::replace(&mut array[index], value))),
+				(Some(index), _) if index == array.len() => {
+					array.push(value);
+					Ok(None)
+				},
+				(_, "-") => {
+					array.push(value);
+					Ok(None)
+				},
+				_ => Err(PatchError::InvalidIndex),
+			},
+			Json::Object(object) => Ok(object.insert(last.clone(), value)),
+			_ => Err(PatchError::NotContainer),
+		};
+	}
+
+	/// Remove the value at the JSON Pointer and get it, where later Array
+	/// elements shift down, otherwise `None` if it doesn't exist
+	pub fn remove_pointer(&mut self, pointer: &str) -> Result<Option<Json>, PatchError>
+	{
+		return self.remove_pointer_with(pointer, &PointerOptions::default());
+	}
+
+	/// Remove the value at the JSON Pointer with the options and get it, where
+	/// later Array elements shift down. The root can't be removed.
+	pub fn remove_pointer_with(&mut self, pointer: &str, options: &PointerOptions) -> Result<Option<Json>, PatchError>
+	{
+		let tokens = parse_pointer(pointer)?;
+		let (last, parents) = match tokens.split_last() {
+			Some(split) => split,
+			None => return Err(PatchError::InvalidPointer),
+		};
+
+		let removed = match find_parent(self, parents, last, false) {
+			Ok(Json::Array(array)) => match parse_index(last) {
+				Some(index) if index < array.len() => Some(array.remove(index)),
+				Some(_) => None,
+				None => return Err(PatchError::InvalidIndex),
+			},
+			Ok(Json::Object(object)) => object.remove(last),
+			Ok(_) => return Err(PatchError::NotContainer),
+			Err(PatchError::NotFound) => None,
+			Err(error) => return Err(error),
+		};
+		return match removed {
+			None if options.require_existing => Err(PatchError::NotFound),
+			removed => Ok(removed),
+		};
+	}
+
+	/// Clone only the value at the JSON Pointer, otherwise `None` if the
+	/// pointer is invalid or the value doesn't exist
+	pub fn clone_pointer(&self, pointer: &str) -> Option<Json>
+	{
+		let tokens = parse_pointer(pointer).ok()?;
+		return find(self, &tokens).cloned();
+	}
+
+	/// Get the JSON Pointer of every value in document order, starting with
+	/// the empty pointer of the root
+	pub fn pointers(&self) -> impl Iterator<Item = String> + '_
+	{
+		return self.pointers_with(false);
+	}
+
+	/// Get the JSON Pointer of every value in document order, or only of the
+	/// scalars and empty containers if `leaves_only` is `true`
+	pub fn pointers_with(&self, leaves_only: bool) -> impl Iterator<Item = String> + '_
+	{
+		let mut stack = vec![(self, String::new())];
+		return core::iter::from_fn(move || {
+			while let Some((value, pointer)) = stack.pop() {
+				let is_leaf = match value {
+					Json::Array(array) => {
+						for (i, element) in array.iter().enumerate().rev() {
+							stack.push((element, format!("{}/{}", pointer, i)));
+						}
+						array.is_empty()
+					},
+					Json::Object(object) => {
+						for (key, member) in object.iter().rev() {
+							stack.push((member, format!("{}/{}", pointer, escape_token(key))));
+						}
+						object.is_empty()
+					},
+					_ => true,
+				};
+				if is_leaf || !leaves_only {
+					return Some(pointer);
+				}
+			}
+			return None;
+		});
+	}
+
+	/// Get the JSON Pointer of the first value in document order which
+	/// matches the predicate, including the root, otherwise `None`
+	pub fn find_path(&self, pred: impl Fn(&Json) -> bool) -> Option<String>
+	{
+		let mut stack = vec![(self, String::new())];
+		while let Some((value, pointer)) = stack.pop() {
+			if pred(value) {
+				return Some(pointer);
+			}
+			match value {
+				Json::Array(array) => {
+					for (i, element) in array.iter().enumerate().rev() {
+						stack.push((element, format!("{}/{}", pointer, i)));
+					}
+				},
+				Json::Object(object) => {
+					for (key, member) in object.iter().rev() {
+						stack.push((member, format!("{}/{}", pointer, escape_token(key))));
+					}
+				},
+				_ => (),
+			}
+		}
+		return None;
+	}
+}
+
+
+/// Find the value of the reference tokens before the last one, otherwise
+/// create the missing containers if allowed
+pub(crate) fn find_parent<'a>(root: &'a mut Json, parents: &[String], last: &str, create_missing: bool) -> Result<&'a mut Json, PatchError>
+{
+	let mut parent = root;
+	for (i, token) in parents.iter().enumerate() {
+		let next_token = parents.get(i + 1).map_or(last, String::as_str);
+		let missing = || match next_token {
+			"-" => Json::Array(Vec::new()),
+			_ => Json::Object(Default::default()),
+		};
+		parent = match parent {
+			Json::Array(array) => match (parse_index(token), token.as_str()) {
+				(Some(index), _) if index < array.len() => &mut array[index],
+				(_, "-") if create_missing => {
+					array.push(missing());
+					let len = array.len();
+					&mut array[len - 1]
+				},
+				_ => return Err(PatchError::NotFound),
+			},
+			Json::Object(object) => match object.entry(token.clone()) {
+				Entry::Occupied(entry) => entry.into_mut(),
+				Entry::Vacant(entry) if create_missing => entry.insert(missing()),
+				Entry::Vacant(_) => return Err(PatchError::NotFound),
+			},
+			_ => return Err(PatchError::NotContainer),
+		};
+	}
+	return Ok(parent);
+}
+
+
+/// Find the value of the reference tokens, otherwise `None`
+pub(crate) fn find<'a>(root: &'a Json, tokens: &[String]) -> Option<&'a Json>
+{
+	let mut value = root;
+	for token in tokens {
+		value = match value {
+			Json::Array(array) => array.get(parse_index(token)?)?,
+			Json::Object(object) => object.get(token)?,
+			_ => return None,
+		};
+	}
+	return Some(value);
+}
+
+
+/// Split the JSON Pointer into its reference tokens without the escape
+/// sequences
+pub(crate) fn parse_pointer(pointer: &str) -> Result<Vec<String>, PatchError>
+{
+	if pointer.is_empty() {
+		return Ok(Vec::new());
+	}
+	let pointer = match pointer.strip_prefix('/') {
+		Some(pointer) => pointer,
+		None => return Err(PatchError::InvalidPointer),
+	};
+
+	let mut tokens = Vec::<String>::new();
+	for escaped_token in pointer.split('/') {
+		let mut token = String::with_capacity(escaped_token.len());
+		let mut chars = escaped_token.chars();
+		while let Some(c) = chars.next() {
+			token.push(match c {
+				'~' => match chars.next() {
+					Some('0') => '~',
+					Some('1') => '/',
+					_ => return Err(PatchError::InvalidPointer),
+				},
+				c => c,
+			});
+		}
+		tokens.push(token);
+	}
+	return Ok(tokens);
+}
+
+
+/// Escape `~` and `/` in the Object key to use it as a reference token
+pub(crate) fn escape_token(key: &str) -> String
+{
+	return key.replace('~', "~0").replace('/', "~1");
+}
+
+
+/// Get the Array index of the reference token, which is `0` or has no leading
+/// zeros, otherwise `None`
+pub(crate) fn parse_index(token: &str) -> Option<usize>
+{
+	if token.is_empty() || !token.bytes().all(|byte| byte.is_ascii_digit()) || (token.len() > 1 && token.starts_with('0')) {
+		return None;
+	}
+	return token.parse().ok();
+}
+
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	/// Parse the JSON data which must be valid
+	fn json(bytes: &[u8]) -> Json
+	{
+		return Json::parse(bytes).unwrap();
+	}
+
+	#[test]
+	fn set_pointer()
+	{
+		let mut value = json(b"{\"a\": [1, 2], \"b/c\": {\"~d\": 3}}");
+		assert!(value.set_pointer("/a/0", Json::Null).is_ok_and(|old| old == Some(Json::Number(1.0))));
+		assert!(value.set_pointer("/a/-", Json::Number(4.0)).is_ok_and(|old| old.is_none()));
+		assert!(value.set_pointer("/a/3", Json::Number(5.0)).is_ok_and(|old| old.is_none()));
+		assert!(value.set_pointer("/b~1c/~0d", Json::Boolean(true)).is_ok_and(|old| old == Some(Json::Number(3.0))));
+		assert_eq!(value.to_string(), "{\"a\":[null,2,4,5],\"b/c\":{\"~d\":true}}");
+		assert_eq!(value.set_pointer("/a/9", Json::Null).err(), Some(PatchError::InvalidIndex));
+		assert_eq!(value.set_pointer("/a/01", Json::Null).err(), Some(PatchError::InvalidIndex));
+		assert_eq!(value.set_pointer("/x/y", Json::Null).err(), Some(PatchError::NotFound));
+		assert_eq!(value.set_pointer("/a/0/b", Json::Null).err(), Some(PatchError::NotContainer));
+		assert_eq!(value.set_pointer("a", Json::Null).err(), Some(PatchError::InvalidPointer));
+		assert_eq!(value.set_pointer("/~2", Json::Null).err(), Some(PatchError::InvalidPointer));
+		assert!(value.set_pointer("", Json::Null).is_ok_and(|old| old.is_some()));
+		assert!(value == Json::Null);
+	}
+
+	#[test]
+	fn set_pointer_create_missing()
+	{
+		let options = PointerOptions { create_missing: true, ..PointerOptions::default() };
+		let mut value = json(b"{}");
+		assert!(value.set_pointer_with("/a/b/-/c", Json::Number(1.0), &options).is_ok());
+		assert!(value.set_pointer_with("/a/b/-", Json::Number(2.0), &options).is_ok());
+		assert_eq!(value.to_string(), "{\"a\":{\"b\":[{\"c\":1},2]}}");
+		assert_eq!(value.set_pointer_with("/a/b/5/c", Json::Null, &options).err(), Some(PatchError::NotFound));
+	}
+
+	#[test]
+	fn remove_pointer()
+	{
+		let mut value = json(b"{\"a\": [1, 2, 3], \"b\": 4}");
+		assert!(value.remove_pointer("/a/0").is_ok_and(|removed| removed == Some(Json::Number(1.0))));
+		assert!(value.remove_pointer("/b").is_ok_and(|removed| removed == Some(Json::Number(4.0))));
+		assert!(value.remove_pointer("/b").is_ok_and(|removed| removed.is_none()));
+		assert!(value.remove_pointer("/x/y").is_ok_and(|removed| removed.is_none()));
+		assert!(value.remove_pointer("/a/5").is_ok_and(|removed| removed.is_none()));
+		assert_eq!(value.to_string(), "{\"a\":[2,3]}");
+		assert_eq!(value.remove_pointer("/a/-").err(), Some(PatchError::InvalidIndex));
+		assert_eq!(value.remove_pointer("").err(), Some(PatchError::InvalidPointer));
+		let options = PointerOptions { require_existing: true, ..PointerOptions::default() };
+		assert_eq!(value.remove_pointer_with("/b", &options).err(), Some(PatchError::NotFound));
+		assert_eq!(value.remove_pointer_with("/x/y", &options).err(), Some(PatchError::NotFound));
+	}
+
+	#[test]
+	fn clone_pointer()
+	{
+		let value = json(b"{\"a\": [{\"b\": 1}], \"\": 2}");
+		assert!(value.clone_pointer("/a/0").is_some_and(|element| element == json(b"{\"b\": 1}")));
+		assert!(value.clone_pointer("/").is_some_and(|member| member == Json::Number(2.0)));
+		assert!(value.clone_pointer("").is_some_and(|root| root == value));
+		assert!(value.clone_pointer("/a/1").is_none());
+		assert!(value.clone_pointer("/a/b").is_none());
+		assert!(value.clone_pointer("a").is_none());
+	}
+
+	#[test]
+	fn pointers()
+	{
+		let value = json(b"{\"a\": [1, []], \"b/c\": {}, \"d\": {\"e\": null}}");
+		assert_eq!(value.pointers().collect::<Vec<_>>(), ["", "/a", "/a/0", "/a/1", "/b~1c", "/d", "/d/e"]);
+		assert_eq!(value.pointers_with(true).collect::<Vec<_>>(), ["/a/0", "/a/1", "/b~1c", "/d/e"]);
+		assert_eq!(json(b"1").pointers_with(true).collect::<Vec<_>>(), [""]);
+		for pointer in value.pointers() {
+			assert!(value.clone_pointer(&pointer).is_some());
+		}
+	}
+
+	#[test]
+	fn find_path()
+	{
+		let value = json(b"{\"a\": [1, {\"b~\": \"x\"}], \"c\": \"x\"}");
+		assert_eq!(value.find_path(|value| *value == Json::String("x".to_string())).as_deref(), Some("/a/1/b~0"));
+		assert_eq!(value.find_path(Json::is_object).as_deref(), Some(""));
+		assert_eq!(value.find_path(Json::is_null), None);
+	}
+}
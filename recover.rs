@@ -0,0 +1,371 @@
+use std::collections::BTreeMap;
+
+use crate::peek_token;
+use crate::Json;
+use crate::ParseError;
+use crate::ParseErrorKind;
+use crate::ParseOptions;
+use crate::Token;
+
+
+impl Json
+{
+	/// Parse a JSON value while recovering from errors, and get a best-effort
+	/// value with every error found, where each invalid token becomes null.
+	/// The value is the same as `parse` if there aren't any errors.
+	pub fn parse_collect_errors(bytes: &[u8]) -> (Option<Json>, Vec<ParseError>)
+	{
+		let mut tokens = Vec::<(usize, Token)>::new();
+		let mut errors = Vec::<ParseError>::new();
+		tokenize_recovering(bytes, &mut tokens, &mut errors);
+		let value = parse_recovering(tokens, bytes.len(), &mut errors);
+		// Report one error for each invalid token, not its placeholder too
+		errors.sort_by_key(|error| error.offset);
+		errors.dedup_by_key(|error| error.offset);
+		return (value, errors);
+	}
+}
+
+
+/// Tokenize the entire content, and replace each invalid token with null after
+/// remembering its error
+fn tokenize_recovering(bytes: &[u8], tokens: &mut Vec<(usize, Token)>, errors: &mut Vec<ParseError>)
+{
+	let mut i = 0;
+	while i < bytes.len() {
+		if let b'\t' | b'\n' | b'\r' | b' ' = bytes[i] {
+			i += 1;
+			continue;
+		}
+		match peek_token(&bytes[i..bytes.len()], &ParseOptions::default()) {
+			Ok((token_len, token)) => {
+				tokens.push((i, token));
+				i += token_len;
+			},
+			// Resynchronize after the whole invalid token, even if the error is
+			// in the middle of a string
+			Err(error) => {
+				let offset = i + error.offset;
+				errors.push(ParseError { kind: error.kind, offset });
+				tokens.push((offset, Token::Null));
+				i += skip_invalid_token(&bytes[i..bytes.len()]);
+			},
+		}
+	}
+}
+
+
+/// Find the end of the invalid token at the start, which is the rest of a
+/// string or the bytes before the next delimiter
+fn skip_invalid_token(remaining_bytes: &[u8]) -> usize
+{
+	let len = remaining_bytes.len();
+	if len == 0 {
+		return 0;
+	}
+
+	if remaining_bytes[0] == b'"' {
+		let mut i = 1;
+		while i < len {
+			match remaining_bytes[i] {
+				b'\\' => i += 2,
+				b'"' => return i + 1,
+				b'\n' => return i,
+				_ => i += 1,
+			}
+		}
+		return len;
+	}
+
+	let mut i = 1;
+	while i < len {
+		match remaining_bytes[i] {
+			b'\t' | b'\n' | b'\r' | b' ' | b'"' | b',' | b':' | b'[' | b']' | b'{' | b'}' => return i,
+			_ => i += 1,
+		}
+	}
+	return len;
+}
+
+
+/// The next token which the container expects
+enum Expect
+{
+	FirstValue,
+	Value,
+	Separator,
+	FirstKey,
+	Key,
+	Colon,
+}
+
+
+/// A container being parsed, with the key of the Object member being parsed
+enum Frame
+{
+	Array(Vec<Json>, Expect),
+	Object(BTreeMap<String, Json>, Option<(String, usize)>, Expect),
+}
+
+
+impl Frame
+{
+	/// Get the container as it is
+	fn into_value(self) -> Json
+	{
+		return match self {
+			Frame::Array(array, _) => Json::Array(array),
+			Frame::Object(object, _, _) => Json::Object(object),
+		};
+	}
+}
+
+
+/// Parse the tokens into the best-effort JSON value, while remembering each
+/// error and skipping or assuming tokens to continue
+fn parse_recovering(tokens: Vec<(usize, Token)>, len: usize, errors: &mut Vec<ParseError>) -> Option<Json>
+{
+	let mut root: Option<Json> = None;
+	let mut stack = Vec::<Frame>::new();
+
+	for (offset, mut token) in tokens {
+		let unexpected_token = ParseError { kind: ParseErrorKind::UnexpectedToken, offset };
+		let starts_value = matches!(
+			token,
+			Token::ArrayBegin | Token::Boolean(_) | Token::Null | Token::Number(_) | Token::ObjectBegin | Token::String(_)
+		);
+
+		// Close the inner containers if the end is for an outer one: [ { ]
+		let closes = match token {
+			Token::ArrayEnd => Some(true),
+			Token::ObjectEnd => Some(false),
+			_ => None,
+		};
+		if let Some(closes_array) = closes {
+			let is_array = |frame: &Frame| matches!(frame, Frame::Array(..));
+			let mismatched = stack.last().is_some_and(|frame| is_array(frame) != closes_array);
+			if mismatched && stack.iter().any(|frame| is_array(frame) == closes_array) {
+				errors.push(unexpected_token);
+				while stack.last().is_some_and(|frame| is_array(frame) != closes_array) {
+					if let Some(frame) = stack.pop() {
+						complete_value(frame.into_value(), &mut stack, &mut root, errors);
+					}
+				}
+			}
+		}
+
+		// Decide whether the token begins a value, ends the container, or is
+		// skipped
+		let mut ends_container = false;
+		match stack.last_mut() {
+			None => {
+				if root.is_some() {
+					errors.push(ParseError { kind: ParseErrorKind::TrailingData, offset });
+					break;
+				}
+				if !starts_value {
+					errors.push(unexpected_token);
+					continue;
+				}
+			},
+			Some(Frame::Array(_, expect)) => match (&*expect, &token) {
+				(Expect::FirstValue, Token::ArrayEnd) | (Expect::Separator, Token::ArrayEnd) => ends_container = true,
+				// [ value , ]
+				(Expect::Value, Token::ArrayEnd) => {
+					errors.push(unexpected_token);
+					ends_container = true;
+				},
+				(Expect::Separator, Token::Comma) => {
+					*expect = Expect::Value;
+					continue;
+				},
+				// Assume the missing comma: [ value value
+				(Expect::Separator, _) if starts_value => errors.push(unexpected_token),
+				(Expect::FirstValue | Expect::Value, _) if starts_value => (),
+				_ => {
+					errors.push(unexpected_token);
+					continue;
+				},
+			},
+			Some(Frame::Object(_, key, expect)) => match (&*expect, &mut token) {
+				(Expect::FirstKey, Token::ObjectEnd) | (Expect::Separator, Token::ObjectEnd) => ends_container = true,
+				// { "key" : value , }
+				// { "key" }
+				// { "key" : }
+				(Expect::Key | Expect::Colon | Expect::Value, Token::ObjectEnd) => {
+					errors.push(unexpected_token);
+					ends_container = true;
+				},
+				(Expect::Separator, Token::Comma) => {
+					*expect = Expect::Key;
+					continue;
+				},
+				(Expect::Colon, Token::Colon) => {
+					*expect = Expect::Value;
+					continue;
+				},
+				(Expect::FirstKey | Expect::Key | Expect::Separator, Token::String(string)) => {
+					// Assume the missing comma: { "key" : value "key"
+					if let Expect::Separator = expect {
+						errors.push(unexpected_token);
+					}
+					*key = Some((core::mem::take(string), offset));
+					*expect = Expect::Colon;
+					continue;
+				},
+				// Skip the member without a value: { "key" : ,
+				(Expect::Colon | Expect::Value, Token::Comma) => {
+					errors.push(unexpected_token);
+					*key = None;
+					*expect = Expect::Key;
+					continue;
+				},
+				// Assume the missing colon: { "key" value
+				(Expect::Colon, _) if starts_value => errors.push(unexpected_token),
+				(Expect::Value, _) if starts_value => (),
+				_ => {
+					errors.push(unexpected_token);
+					continue;
+				},
+			},
+		}
+
+		// Begin the value, or complete the scalar or container
+		let value = match ends_container {
+			true => match stack.pop() {
+				Some(frame) => frame.into_value(),
+				None => unreachable!(),
+			},
+			false => match token {
+				Token::ArrayBegin => {
+					stack.push(Frame::Array(Vec::new(), Expect::FirstValue));
+					continue;
+				},
+				Token::ObjectBegin => {
+					stack.push(Frame::Object(BTreeMap::new(), None, Expect::FirstKey));
+					continue;
+				},
+				Token::Boolean(boolean) => Json::Boolean(boolean),
+				Token::Null => Json::Null,
+				Token::Number(number) => Json::Number(number),
+				Token::String(string) => Json::String(string),
+				_ => unreachable!(),
+			},
+		};
+		complete_value(value, &mut stack, &mut root, errors);
+	}
+
+	// Close every container which didn't end
+	if !stack.is_empty() {
+		errors.push(ParseError { kind: ParseErrorKind::UnexpectedEnd, offset: len });
+	}
+	while let Some(frame) = stack.pop() {
+		complete_value(frame.into_value(), &mut stack, &mut root, errors);
+	}
+
+	if root.is_none() && errors.is_empty() {
+		errors.push(ParseError { kind: ParseErrorKind::UnexpectedEnd, offset: len });
+	}
+	return root;
+}
+
+
+/// Put the complete value into its container, otherwise make it the root
+fn complete_value(value: Json, stack: &mut [Frame], root: &mut Option<Json>, errors: &mut Vec<ParseError>)
+{
+	match stack.last_mut() {
+		None => *root = Some(value),
+		Some(Frame::Array(array, expect)) => {
+			array.push(value);
+			*expect = Expect::Separator;
+		},
+		Some(Frame::Object(object, key, expect)) => {
+			if let Some((key, key_offset)) = key.take() {
+				if object.contains_key(&key) {
+					errors.push(ParseError { kind: ParseErrorKind::DuplicateKey, offset: key_offset });
+				}
+				object.insert(key, value);
+			}
+			*expect = Expect::Separator;
+		},
+	}
+}
+
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	#[test]
+	fn resync_after_error_inside_string()
+	{
+		let (value, errors) = Json::parse_collect_errors(b"[\"a\x01b\", \"c\", 1]");
+		assert_eq!(errors, vec![ParseError { kind: ParseErrorKind::ControlCharacter(0x01), offset: 3 }]);
+		assert_eq!(value.unwrap().to_string(), "[null,\"c\",1]");
+	}
+
+	#[test]
+	fn resync_after_invalid_utf8_and_surrogate()
+	{
+		let (value, errors) = Json::parse_collect_errors(b"[\"\xff\", \"\\ud800\", true]");
+		assert_eq!(errors.len(), 2);
+		assert_eq!(value.unwrap().to_string(), "[null,null,true]");
+	}
+
+	#[test]
+	fn two_separate_mistakes()
+	{
+		let (value, errors) = Json::parse_collect_errors(b"[1 2, {\"a\" 3}]");
+		assert_eq!(
+			errors,
+			vec![
+				ParseError { kind: ParseErrorKind::UnexpectedToken, offset: 3 },
+				ParseError { kind: ParseErrorKind::UnexpectedToken, offset: 11 },
+			]
+		);
+		assert_eq!(value.unwrap().to_string(), "[1,2,{\"a\":3}]");
+	}
+
+	#[test]
+	fn same_as_parse_without_errors()
+	{
+		let bytes = b"{\"a\": [1, \"b\", null], \"c\": {}}";
+		let (value, errors) = Json::parse_collect_errors(bytes);
+		assert!(errors.is_empty());
+		assert!(value == Json::parse(bytes));
+	}
+
+	#[test]
+	fn unterminated_containers()
+	{
+		let (value, errors) = Json::parse_collect_errors(b"{\"a\": [1, 2");
+		assert_eq!(errors, vec![ParseError { kind: ParseErrorKind::UnexpectedEnd, offset: 11 }]);
+		assert_eq!(value.unwrap().to_string(), "{\"a\":[1,2]}");
+		let (value, errors) = Json::parse_collect_errors(b"");
+		assert_eq!(errors, vec![ParseError { kind: ParseErrorKind::UnexpectedEnd, offset: 0 }]);
+		assert!(value.is_none());
+	}
+
+	#[test]
+	fn mismatched_brackets()
+	{
+		let (value, errors) = Json::parse_collect_errors(b"[1, {\"a\": [2, 3]], 4]");
+		assert_eq!(errors, vec![ParseError { kind: ParseErrorKind::UnexpectedToken, offset: 16 }, ParseError { kind: ParseErrorKind::TrailingData, offset: 17 }]);
+		assert_eq!(value.unwrap().to_string(), "[1,{\"a\":[2,3]}]");
+	}
+
+	#[test]
+	fn invalid_tokens_and_trailing_data()
+	{
+		let (value, errors) = Json::parse_collect_errors(b"[tru, 1] 2");
+		assert_eq!(
+			errors,
+			vec![
+				ParseError { kind: ParseErrorKind::InvalidToken, offset: 1 },
+				ParseError { kind: ParseErrorKind::TrailingData, offset: 9 },
+			]
+		);
+		assert_eq!(value.unwrap().to_string(), "[null,1]");
+	}
+}
@@ -0,0 +1,267 @@
+use core::fmt;
+
+use crate::pointer::escape_token;
+use crate::Json;
+
+
+/// The reason a value doesn't match the schema and the JSON Pointer of the
+/// value
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SchemaError
+{
+	pub kind: SchemaErrorKind,
+	pub pointer: String,
+}
+
+
+/// The keyword of the schema which the value doesn't match
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SchemaErrorKind
+{
+	/// The value isn't any of the values of `enum`
+	Enum,
+	/// The Array has more elements than `maxItems`
+	MaxItems,
+	/// The String has more characters than `maxLength`
+	MaxLength,
+	/// The Number is greater than `maximum`
+	Maximum,
+	/// The Array has fewer elements than `minItems`
+	MinItems,
+	/// The String has fewer characters than `minLength`
+	MinLength,
+	/// The Number is less than `minimum`
+	Minimum,
+	/// The Object member of `required` is missing
+	Required,
+	/// The value isn't any of the types of `type`
+	Type,
+}
+
+
+impl fmt::Display for SchemaError
+{
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result
+	{
+		let reason = match self.kind {
+			SchemaErrorKind::Enum => "not in enum",
+			SchemaErrorKind::MaxItems => "too many items",
+			SchemaErrorKind::MaxLength => "too long",
+			SchemaErrorKind::Maximum => "greater than maximum",
+			SchemaErrorKind::MinItems => "too few items",
+			SchemaErrorKind::MinLength => "too short",
+			SchemaErrorKind::Minimum => "less than minimum",
+			SchemaErrorKind::Required => "missing required member",
+			SchemaErrorKind::Type => "wrong type",
+		};
+		return write!(formatter, "{} at \"{}\"", reason, self.pointer);
+	}
+}
+
+
+impl std::error::Error for SchemaError
+{
+}
+
+
+impl Json
+{
+	/// Validate the value against a subset of JSON Schema, and get every error
+	/// otherwise. The keywords are `type`, `required`, `properties`, `items`,
+	/// `enum`, `minimum`, `maximum`, `minLength`, `maxLength`, `minItems`, and
+	/// `maxItems`. Other keywords are ignored.
+	pub fn validate_schema(&self, schema: &Json) -> Result<(), Vec<SchemaError>>
+	{
+		let mut errors = Vec::<SchemaError>::new();
+		let mut stack = vec![(self, schema, String::new())];
+
+		while let Some((value, schema, pointer)) = stack.pop() {
+			let schema = match schema {
+				Json::Object(schema) => schema,
+				_ => continue,
+			};
+			let mut error = |kind| errors.push(SchemaError { kind, pointer: pointer.clone() });
+
+			if let Some(types) = schema.get("type") {
+				let matches = match types {
+					Json::Array(types) => types.iter().any(|name| has_type(value, name)),
+					name => has_type(value, name),
+				};
+				if !matches {
+					error(SchemaErrorKind::Type);
+				}
+			}
+			if let Some(Json::Array(values)) = schema.get("enum") {
+				if !values.contains(value) {
+					error(SchemaErrorKind::Enum);
+				}
+			}
+
+			match value {
+				Json::Array(array) => {
+					let len = array.len() as f64;
+					if schema.get("minItems").is_some_and(|min| is_number_above(min, len)) {
+						error(SchemaErrorKind::MinItems);
+					}
+					if schema.get("maxItems").is_some_and(|max| is_number_below(max, len)) {
+						error(SchemaErrorKind::MaxItems);
+					}
+					if let Some(items) = schema.get("items") {
+						for (i, element) in array.iter().enumerate().rev() {
+							stack.push((element, items, format!("{}/{}", pointer, i)));
+						}
+					}
+				},
+				Json::Number(number) => {
+					if schema.get("minimum").is_some_and(|min| is_number_above(min, *number)) {
+						error(SchemaErrorKind::Minimum);
+					}
+					if schema.get("maximum").is_some_and(|max| is_number_below(max, *number)) {
+						error(SchemaErrorKind::Maximum);
+					}
+				},
+				Json::Object(object) => {
+					if let Some(Json::Array(required)) = schema.get("required") {
+						for key in required {
+							if let Json::String(key) = key {
+								if !object.contains_key(key) {
+									errors.push(SchemaError {
+										kind: SchemaErrorKind::Required,
+										pointer: format!("{}/{}", pointer, escape_token(key)),
+									});
+								}
+							}
+						}
+					}
+					if let Some(Json::Object(properties)) = schema.get("properties") {
+						for (key, property) in properties.iter().rev() {
+							if let Some(member) = object.get(key) {
+								stack.push((member, property, format!("{}/{}", pointer, escape_token(key))));
+							}
+						}
+					}
+				},
+				Json::String(string) => {
+					let len = string.chars().count() as f64;
+					if schema.get("minLength").is_some_and(|min| is_number_above(min, len)) {
+						error(SchemaErrorKind::MinLength);
+					}
+					if schema.get("maxLength").is_some_and(|max| is_number_below(max, len)) {
+						error(SchemaErrorKind::MaxLength);
+					}
+				},
+				_ => (),
+			}
+		}
+
+		return match errors.is_empty() {
+			true => Ok(()),
+			false => Err(errors),
+		};
+	}
+}
+
+
+/// Check if the value has the type named by the schema, where an integer is a
+/// Number without a fraction
+fn has_type(value: &Json, name: &Json) -> bool
+{
+	let name = match name {
+		Json::String(name) => name.as_str(),
+		_ => return false,
+	};
+	return match (name, value) {
+		("array", Json::Array(_)) => true,
+		("boolean", Json::Boolean(_)) => true,
+		("integer", Json::Number(number)) => number.fract() == 0.0,
+		("null", Json::Null) => true,
+		("number", Json::Number(_)) => true,
+		("object", Json::Object(_)) => true,
+		("string", Json::String(_)) => true,
+		_ => false,
+	};
+}
+
+
+/// Check if the limit is a Number greater than the number
+fn is_number_above(limit: &Json, number: f64) -> bool
+{
+	return matches!(limit, Json::Number(limit) if *limit > number);
+}
+
+
+/// Check if the limit is a Number less than the number
+fn is_number_below(limit: &Json, number: f64) -> bool
+{
+	return matches!(limit, Json::Number(limit) if *limit < number);
+}
+
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	/// Parse the JSON data which must be valid
+	fn json(bytes: &[u8]) -> Json
+	{
+		return Json::parse(bytes).unwrap();
+	}
+
+	/// Validate the value against the schema and get the errors
+	fn errors(value: &[u8], schema: &[u8]) -> Vec<(SchemaErrorKind, String)>
+	{
+		return match json(value).validate_schema(&json(schema)) {
+			Ok(()) => Vec::new(),
+			Err(errors) => errors.into_iter().map(|error| (error.kind, error.pointer)).collect(),
+		};
+	}
+
+	#[test]
+	fn valid()
+	{
+		let schema = b"{
+			\"type\": \"object\",
+			\"required\": [\"id\"],
+			\"properties\": {
+				\"id\": {\"type\": \"integer\", \"minimum\": 1},
+				\"tags\": {\"type\": \"array\", \"maxItems\": 2, \"items\": {\"type\": \"string\", \"minLength\": 1}},
+				\"kind\": {\"enum\": [\"a\", \"b\", null]},
+				\"x-unknown\": {\"pattern\": \"^$\"}
+			}
+		}";
+		assert!(errors(b"{\"id\": 1, \"tags\": [\"x\"], \"kind\": null, \"x-unknown\": 1}", schema).is_empty());
+		assert!(errors(b"{\"id\": 2.0}", schema).is_empty());
+		assert!(errors(b"1", b"{}").is_empty());
+		assert!(errors(b"1", b"true").is_empty());
+	}
+
+	#[test]
+	fn every_error()
+	{
+		let schema = b"{
+			\"required\": [\"id\", \"a/b\"],
+			\"properties\": {
+				\"id\": {\"type\": [\"integer\", \"null\"], \"maximum\": 10},
+				\"tags\": {\"minItems\": 2, \"items\": {\"maxLength\": 2, \"minLength\": 1}},
+				\"list\": {\"maxItems\": 1},
+				\"n\": {\"minimum\": 0},
+				\"kind\": {\"enum\": [\"a\"]}
+			}
+		}";
+		let expected = [
+			(SchemaErrorKind::Required, "/a~1b".to_string()),
+			(SchemaErrorKind::Type, "/id".to_string()),
+			(SchemaErrorKind::Maximum, "/id".to_string()),
+			(SchemaErrorKind::Enum, "/kind".to_string()),
+			(SchemaErrorKind::MaxItems, "/list".to_string()),
+			(SchemaErrorKind::Minimum, "/n".to_string()),
+			(SchemaErrorKind::MinItems, "/tags".to_string()),
+			(SchemaErrorKind::MaxLength, "/tags/0".to_string()),
+		];
+		assert_eq!(errors(b"{\"id\": 10.5, \"tags\": [\"\xc3\xa9\xc3\xa9\xc3\xa9\"], \"list\": [1, 2], \"n\": -1, \"kind\": \"b\"}", schema), expected);
+		assert_eq!(errors(b"{\"tags\": [\"\", \"\xc3\xa9\xc3\xa9\"]}", b"{\"properties\": {\"tags\": {\"items\": {\"minLength\": 1, \"maxLength\": 2}}}}"), [(SchemaErrorKind::MinLength, "/tags/0".to_string())]);
+		let error = SchemaError { kind: SchemaErrorKind::Required, pointer: "/a".to_string() };
+		assert_eq!(error.to_string(), "missing required member at \"/a\"");
+	}
+}
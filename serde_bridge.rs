@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+
+use crate::Json;
+
+
+impl Json
+{
+	/// Convert to the equivalent `serde_json` value without recursion, where
+	/// non-finite numbers become null since `serde_json` can't represent them
+	pub fn to_serde(&self) -> serde_json::Value
+	{
+		/// A container being converted, with the key of the member being
+		/// converted
+		enum Frame<'a>
+		{
+			Array(core::slice::Iter<'a, Json>, Vec<serde_json::Value>),
+			Object(std::collections::btree_map::Iter<'a, String, Json>, serde_json::Map<String, serde_json::Value>, String),
+		}
+
+		let mut stack = Vec::<Frame>::new();
+		let mut next = self;
+
+		loop {
+			// Begin the container, otherwise convert the scalar
+			let mut value = match next {
+				Json::Array(array) => {
+					stack.push(Frame::Array(array.iter(), Vec::with_capacity(array.len())));
+					None
+				},
+				Json::Boolean(boolean) => Some(serde_json::Value::Bool(*boolean)),
+				Json::Null => Some(serde_json::Value::Null),
+				Json::Number(number) => match serde_json::Number::from_f64(*number) {
+					Some(number) => Some(serde_json::Value::Number(number)),
+					None => Some(serde_json::Value::Null),
+				},
+				Json::Object(object) => {
+					stack.push(Frame::Object(object.iter(), serde_json::Map::new(), String::new()));
+					None
+				},
+				Json::String(string) => Some(serde_json::Value::String(string.clone())),
+			};
+
+			// Put each complete value into its container until the next value
+			loop {
+				let found = match stack.last_mut() {
+					None => match value {
+						Some(value) => return value,
+						None => unreachable!(),
+					},
+					Some(Frame::Array(elements, array)) => {
+						if let Some(value) = value.take() {
+							array.push(value);
+						}
+						elements.next()
+					},
+					Some(Frame::Object(members, object, key)) => {
+						if let Some(value) = value.take() {
+							object.insert(core::mem::take(key), value);
+						}
+						members.next().map(|(member_key, member)| {
+							key.clone_from(member_key);
+							member
+						})
+					},
+				};
+				if let Some(found) = found {
+					next = found;
+					break;
+				}
+				value = match stack.pop() {
+					Some(Frame::Array(_, array)) => Some(serde_json::Value::Array(array)),
+					Some(Frame::Object(_, object, _)) => Some(serde_json::Value::Object(object)),
+					None => unreachable!(),
+				};
+			}
+		}
+	}
+
+	/// Convert from the equivalent `serde_json` value without recursion, where
+	/// every number becomes an `f64`
+	pub fn from_serde(value: &serde_json::Value) -> Json
+	{
+		/// A container being converted, with the key of the member being
+		/// converted
+		enum Frame<'a>
+		{
+			Array(core::slice::Iter<'a, serde_json::Value>, Vec<Json>),
+			Object(serde_json::map::Iter<'a>, BTreeMap<String, Json>, String),
+		}
+
+		let mut stack = Vec::<Frame>::new();
+		let mut next = value;
+
+		loop {
+			// Begin the container, otherwise convert the scalar
+			let mut value = match next {
+				serde_json::Value::Array(array) => {
+					stack.push(Frame::Array(array.iter(), Vec::with_capacity(array.len())));
+					None
+				},
+				serde_json::Value::Bool(boolean) => Some(Json::Boolean(*boolean)),
+				serde_json::Value::Null => Some(Json::Null),
+				serde_json::Value::Number(number) => Some(Json::Number(number.as_f64().unwrap_or(f64::NAN))),
+				serde_json::Value::Object(object) => {
+					stack.push(Frame::Object(object.iter(), BTreeMap::new(), String::new()));
+					None
+				},
+				serde_json::Value::String(string) => Some(Json::String(string.clone())),
+			};
+
+			// Put each complete value into its container until the next value
+			loop {
+				let found = match stack.last_mut() {
+					None => match value {
+						Some(value) => return value,
+						None => unreachable!(),
+					},
+					Some(Frame::Array(elements, array)) => {
+						if let Some(value) = value.take() {
+							array.push(value);
+						}
+						elements.next()
+					},
+					Some(Frame::Object(members, object, key)) => {
+						if let Some(value) = value.take() {
+							object.insert(core::mem::take(key), value);
+						}
+						members.next().map(|(member_key, member)| {
+							key.clone_from(member_key);
+							member
+						})
+					},
+				};
+				if let Some(found) = found {
+					next = found;
+					break;
+				}
+				value = match stack.pop() {
+					Some(Frame::Array(_, array)) => Some(Json::Array(array)),
+					Some(Frame::Object(_, object, _)) => Some(Json::Object(object)),
+					None => unreachable!(),
+				};
+			}
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	/// Drop the `serde_json` value without recursion, since its own drop
+	/// recurses
+	fn drop_serde(value: serde_json::Value)
+	{
+		let mut stack = vec![value];
+		while let Some(value) = stack.pop() {
+			match value {
+				serde_json::Value::Array(array) => stack.extend(array),
+				serde_json::Value::Object(object) => stack.extend(object.into_iter().map(|(_, member)| member)),
+				_ => (),
+			}
+		}
+	}
+
+	#[test]
+	fn round_trip()
+	{
+		let value = Json::parse(b"{\"a\": [1, 2.5, true, null], \"b\": {\"c\": \"d\"}}").unwrap();
+		let serde = value.to_serde();
+		assert_eq!(serde, serde_json::json!({"a": [1.0, 2.5, true, null], "b": {"c": "d"}}));
+		assert!(Json::from_serde(&serde) == value);
+	}
+
+	#[test]
+	fn non_finite_becomes_null()
+	{
+		assert_eq!(Json::Number(f64::NAN).to_serde(), serde_json::Value::Null);
+	}
+
+	#[test]
+	fn deep_nesting()
+	{
+		let mut value = Json::Null;
+		for _ in 0..200_000 {
+			value = Json::Array(vec![value]);
+		}
+		let serde = value.to_serde();
+		assert!(Json::from_serde(&serde) == value);
+		drop_serde(serde);
+	}
+}
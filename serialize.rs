@@ -0,0 +1,1299 @@
+use core::fmt;
+use core::fmt::Write as _;
+use core::hash::Hasher;
+use std::collections::btree_map;
+use std::io;
+use std::io::Write;
+
+use crate::peek_token;
+use crate::Json;
+use crate::ParseError;
+use crate::ParseErrorKind;
+use crate::ParseOptions;
+use crate::SourceText;
+use crate::Token;
+
+
+impl fmt::Display for Json
+{
+	/// Serialize the JSON value without any whitespace
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result
+	{
+		let mut output = String::with_capacity(estimated_len(self));
+		if serialize(self, &mut output, &SerializeOptions::default()).is_err() {
+			return Err(fmt::Error);
+		}
+		return formatter.write_str(&output);
+	}
+}
+
+
+/// Options for the format of serialized JSON
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SerializeOptions<'a>
+{
+	/// Escape `/` as `\/` inside strings, like for embedding in HTML scripts
+	pub escape_forward_slash: bool,
+	/// Write each container on multiple lines indented by two spaces, unless it
+	/// fits on one line within this many characters
+	pub max_width: Option<usize>,
+	/// The notation of the numbers
+	pub number_format: NumberFormat,
+	/// How to serialize NaN and infinite numbers, which standard JSON can't
+	/// represent
+	pub non_finite: NonFinitePolicy,
+	/// Write a Number without a fraction in the range of `i64` as an integer,
+	/// like `5` instead of `5.0` or `1e5`, whatever the number format
+	pub integer_floats: bool,
+	/// Write `-0` as `0`, so values which are equal serialize the same
+	pub normalize_negative_zero: bool,
+	/// The source text from `Json::parse_with_source_text`, which is written
+	/// instead of each Number, String, and key which still has the same value
+	pub source_text: Option<&'a SourceText>,
+}
+
+
+/// The notation for serializing numbers
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum NumberFormat
+{
+	/// The fewest characters which parse back to the exact same value, like
+	/// `1`, `0.5`, or `1e-7`
+	#[default]
+	Shortest,
+	/// Decimal notation with at least one digit after the point, like `1.0`
+	/// or `0.0000001`
+	AlwaysDecimal,
+	/// Decimal notation rounded to this many digits after the point, like
+	/// `3.14` for 2
+	FixedDecimals(u8),
+}
+
+
+/// How to serialize NaN and infinite numbers
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum NonFinitePolicy
+{
+	/// Fail to serialize
+	Error,
+	/// Write `null` like JavaScript
+	#[default]
+	Null,
+	/// Write `NaN`, `Infinity`, or `-Infinity` like JSON5, which isn't
+	/// standard JSON
+	AllowLiteral,
+}
+
+
+/// The reason serializing failed
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SerializeError
+{
+	/// A Number is NaN or infinite, but the options don't allow it
+	NonFinite,
+}
+
+
+impl fmt::Display for SerializeError
+{
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result
+	{
+		let reason = match self {
+			SerializeError::NonFinite => "non-finite number",
+		};
+		return formatter.write_str(reason);
+	}
+}
+
+
+impl std::error::Error for SerializeError
+{
+}
+
+
+/// The reason formatting JSON data failed
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FormatError
+{
+	Parse(ParseError),
+	Serialize(SerializeError),
+}
+
+
+impl fmt::Display for FormatError
+{
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result
+	{
+		return match self {
+			FormatError::Parse(error) => error.fmt(formatter),
+			FormatError::Serialize(error) => error.fmt(formatter),
+		};
+	}
+}
+
+
+impl std::error::Error for FormatError
+{
+}
+
+
+impl Json
+{
+	/// Serialize the JSON value with the options, otherwise fail if a Number
+	/// isn't finite and the options don't allow it
+	pub fn to_string_with(&self, options: &SerializeOptions) -> Result<String, SerializeError>
+	{
+		let mut output = String::with_capacity(estimated_len(self));
+		serialize(self, &mut output, options)?;
+		return Ok(output);
+	}
+
+	/// Count the bytes of the serialization without any whitespace, like
+	/// `to_string`, without building it
+	pub fn serialized_len(&self) -> usize
+	{
+		let mut len = 0;
+		let mut number_output = String::new();
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			// The brackets and commas, or the scalar
+			len += match value {
+				Json::Array(array) => {
+					stack.extend(array);
+					2 + array.len().saturating_sub(1)
+				},
+				Json::Boolean(true) => 4,
+				Json::Boolean(false) => 5,
+				Json::Null => 4,
+				Json::Number(number) => {
+					number_output.clear();
+					let _ = serialize_number(*number, &mut number_output, &SerializeOptions::default());
+					number_output.len()
+				},
+				Json::Object(object) => {
+					for key in object.keys() {
+						len += serialized_string_len(key) + 1;
+					}
+					stack.extend(object.values());
+					2 + object.len().saturating_sub(1)
+				},
+				Json::String(string) => serialized_string_len(string),
+			};
+		}
+		return len;
+	}
+
+	/// Parse the JSON data and serialize it again with the options, like for
+	/// reformatting a file
+	pub fn format_bytes(bytes: &[u8], options: &SerializeOptions) -> Result<String, FormatError>
+	{
+		let value = Json::parse_detailed(bytes).map_err(FormatError::Parse)?;
+		return value.to_string_with(options).map_err(FormatError::Serialize);
+	}
+
+	/// Serialize to canonical bytes for hashing, which are identical for equal
+	/// values since the keys are sorted, the numbers are shortest with `-0` as
+	/// `0`, and there's no whitespace
+	pub fn to_hash_bytes(&self) -> Vec<u8>
+	{
+		let mut output = String::with_capacity(estimated_len(self));
+		let _ = serialize(self, &mut output, &HASH_OPTIONS);
+		return output.into_bytes();
+	}
+
+	/// Serialize the JSON value without any whitespace into bytes, like for
+	/// writing to a byte sink
+	pub fn to_bytes(&self) -> Vec<u8>
+	{
+		let mut output = String::with_capacity(estimated_len(self));
+		let _ = serialize(self, &mut output, &SerializeOptions::default());
+		return output.into_bytes();
+	}
+
+	/// Get the 64-bit FNV-1a hash of the canonical bytes from `to_hash_bytes`
+	/// as 16 hexadecimal digits, which is the same for equal values. It isn't
+	/// a cryptographic hash.
+	pub fn fingerprint(&self) -> String
+	{
+		const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+		const PRIME: u64 = 0x100000001b3;
+
+		let mut hash = OFFSET_BASIS;
+		for byte in self.to_hash_bytes() {
+			hash = (hash ^ byte as u64).wrapping_mul(PRIME);
+		}
+		return format!("{:016x}", hash);
+	}
+
+	/// Feed the same canonical bytes as `to_hash_bytes` into the hasher,
+	/// directly from the JSON data without building a value or keeping the
+	/// tokens. The data is checked and scanned once for the members of every
+	/// Object, and only their offsets are kept to write them sorted by key. If
+	/// the data isn't valid JSON, then the hasher isn't fed anything.
+	pub fn hash_stream(bytes: &[u8], hasher: &mut impl Hasher) -> Result<(), ParseError>
+	{
+		/// A container being hashed, which for an Object has the sorted members
+		/// left, where it ends, and whether its first member is next
+		enum State<'a>
+		{
+			Array,
+			Object(core::slice::Iter<'a, (usize, usize)>, usize, bool),
+		}
+
+		const CHUNK_LEN: usize = 4096;
+
+		let objects = scan_objects(bytes)?;
+		let mut i = 0;
+		let mut output = String::new();
+		let mut stack = Vec::<State>::new();
+		let mut next = true;
+
+		loop {
+			// Write the scalar or the beginning of the container
+			if next {
+				next = false;
+				match next_token(bytes, &mut i)? {
+					(_, Token::ArrayBegin) => {
+						output.push('[');
+						stack.push(State::Array);
+					},
+					(_, Token::Boolean(true)) => output.push_str("true"),
+					(_, Token::Boolean(false)) => output.push_str("false"),
+					(_, Token::Null) => output.push_str("null"),
+					(_, Token::Number(number)) => {
+						let _ = serialize_number(number, &mut output, &HASH_OPTIONS);
+					},
+					(offset, Token::ObjectBegin) => {
+						let object = match objects.binary_search_by_key(&offset, |object| object.begin) {
+							Ok(object_i) => &objects[object_i],
+							Err(_) => unreachable!(),
+						};
+						output.push('{');
+						stack.push(State::Object(object.members.iter(), object.end, true));
+					},
+					(_, Token::String(string)) => serialize_string(&string, &mut output, &HASH_OPTIONS),
+					_ => unreachable!(),
+				}
+			}
+
+			if output.len() >= CHUNK_LEN {
+				hasher.write(output.as_bytes());
+				output.clear();
+			}
+
+			// Find the next value, otherwise write the end of the container
+			match stack.last_mut() {
+				None => break,
+				Some(State::Array) => {
+					let start = i;
+					match next_token(bytes, &mut i)? {
+						(_, Token::ArrayEnd) => {
+							output.push(']');
+							stack.pop();
+						},
+						(_, Token::Comma) => {
+							output.push(',');
+							next = true;
+						},
+						_ => {
+							// Peek the first value again when writing it
+							i = start;
+							next = true;
+						},
+					}
+				},
+				Some(State::Object(members, end, first)) => match members.next() {
+					Some(&(key_i, value_i)) => {
+						if !*first {
+							output.push(',');
+						}
+						*first = false;
+						serialize_string(&key_at(bytes, key_i), &mut output, &HASH_OPTIONS);
+						output.push(':');
+						i = value_i;
+						next = true;
+					},
+					None => {
+						output.push('}');
+						i = *end;
+						stack.pop();
+					},
+				},
+			}
+		}
+
+		hasher.write(output.as_bytes());
+		return Ok(());
+	}
+}
+
+
+/// Find the next token after the whitespace with its offset, and move past it
+fn next_token(bytes: &[u8], i: &mut usize) -> Result<(usize, Token), ParseError>
+{
+	while *i < bytes.len() && matches!(bytes[*i], b'\t' | b'\n' | b'\r' | b' ') {
+		*i += 1;
+	}
+	if *i == bytes.len() {
+		return Err(ParseError { kind: ParseErrorKind::UnexpectedEnd, offset: bytes.len() });
+	}
+	let offset = *i;
+	return match peek_token(&bytes[offset..bytes.len()], &ParseOptions::default()) {
+		Ok((token_len, token)) => {
+			*i += token_len;
+			Ok((offset, token))
+		},
+		Err(error) => Err(ParseError { kind: error.kind, offset: offset + error.offset }),
+	};
+}
+
+
+/// Get the key which is the valid String token at the offset
+fn key_at(bytes: &[u8], mut i: usize) -> String
+{
+	return match next_token(bytes, &mut i) {
+		Ok((_, Token::String(key))) => key,
+		_ => unreachable!(),
+	};
+}
+
+
+/// An Object in the JSON data, with the key offset and value offset of each
+/// member sorted by key
+struct ScannedObject
+{
+	begin: usize,
+	end: usize,
+	members: Vec<(usize, usize)>,
+}
+
+
+/// Check the JSON data in one pass without keeping the tokens, and get every
+/// Object in the order they begin
+fn scan_objects(bytes: &[u8]) -> Result<Vec<ScannedObject>, ParseError>
+{
+	enum State
+	{
+		Value,
+		ArrayFirst,
+		ArrayNext,
+		ObjectFirst,
+		ObjectKey,
+		ObjectColon,
+		ObjectNext,
+	}
+
+	/// A container around the current token, which for an Object is its index
+	/// and the offsets of its members so far
+	enum Frame
+	{
+		Array,
+		Object(usize, Vec<(usize, usize)>),
+	}
+
+	let mut objects = Vec::<ScannedObject>::new();
+	let mut stack = Vec::<Frame>::new();
+	let mut state = State::Value;
+	let mut i = 0;
+
+	loop {
+		let (offset, token) = next_token(bytes, &mut i)?;
+		match (state, token) {
+			// [ [
+			// [ {
+			(State::Value | State::ArrayFirst, Token::ArrayBegin) => {
+				stack.push(Frame::Array);
+				state = State::ArrayFirst;
+				continue;
+			},
+			(State::Value | State::ArrayFirst, Token::ObjectBegin) => {
+				objects.push(ScannedObject { begin: offset, end: 0, members: Vec::new() });
+				stack.push(Frame::Object(objects.len() - 1, Vec::new()));
+				state = State::ObjectFirst;
+				continue;
+			},
+			// [ value
+			(State::Value | State::ArrayFirst, Token::Boolean(_) | Token::Null | Token::Number(_) | Token::String(_)) => (),
+			// [ ]
+			// value ]
+			(State::ArrayFirst | State::ArrayNext, Token::ArrayEnd) => {
+				stack.pop();
+			},
+			// value ,
+			(State::ArrayNext, Token::Comma) => {
+				state = State::Value;
+				continue;
+			},
+			// { }
+			// value }
+			(State::ObjectFirst | State::ObjectNext, Token::ObjectEnd) => {
+				if let Some(Frame::Object(object_i, members)) = stack.pop() {
+					objects[object_i].end = i;
+					objects[object_i].members = sort_members(bytes, members)?;
+				}
+			},
+			// { "key"
+			// , "key"
+			(State::ObjectFirst | State::ObjectKey, Token::String(_)) => {
+				if let Some(Frame::Object(_, members)) = stack.last_mut() {
+					members.push((offset, 0));
+				}
+				state = State::ObjectColon;
+				continue;
+			},
+			// "key" :
+			(State::ObjectColon, Token::Colon) => {
+				if let Some(Frame::Object(_, members)) = stack.last_mut() {
+					if let Some(member) = members.last_mut() {
+						member.1 = i;
+					}
+				}
+				state = State::Value;
+				continue;
+			},
+			// value ,
+			(State::ObjectNext, Token::Comma) => {
+				state = State::ObjectKey;
+				continue;
+			},
+			_ => return Err(ParseError { kind: ParseErrorKind::UnexpectedToken, offset }),
+		}
+
+		// Continue the container of the complete value
+		state = match stack.last() {
+			None => break,
+			Some(Frame::Array) => State::ArrayNext,
+			Some(Frame::Object(..)) => State::ObjectNext,
+		};
+	}
+
+	while i < bytes.len() && matches!(bytes[i], b'\t' | b'\n' | b'\r' | b' ') {
+		i += 1;
+	}
+	if i < bytes.len() {
+		return Err(ParseError { kind: ParseErrorKind::TrailingData, offset: i });
+	}
+	return Ok(objects);
+}
+
+
+/// Sort the key offsets and value offsets of the members of an Object by key,
+/// otherwise fail if a key is repeated
+fn sort_members(bytes: &[u8], members: Vec<(usize, usize)>) -> Result<Vec<(usize, usize)>, ParseError>
+{
+	let mut members = members.into_iter()
+		.map(|(key_i, value_i)| (key_at(bytes, key_i), key_i, value_i))
+		.collect::<Vec<(String, usize, usize)>>();
+	members.sort_by(|a, b| a.0.cmp(&b.0));
+	let duplicate_offset = members.windows(2)
+		.filter(|pair| pair[0].0 == pair[1].0)
+		.map(|pair| pair[0].1.max(pair[1].1))
+		.min();
+	if let Some(offset) = duplicate_offset {
+		return Err(ParseError { kind: ParseErrorKind::DuplicateKey, offset });
+	}
+	return Ok(members.into_iter().map(|(_, key_i, value_i)| (key_i, value_i)).collect());
+}
+
+
+/// The options of the canonical bytes for hashing
+const HASH_OPTIONS: SerializeOptions<'static> = SerializeOptions {
+	escape_forward_slash: false,
+	max_width: None,
+	number_format: NumberFormat::Shortest,
+	non_finite: NonFinitePolicy::Null,
+	integer_floats: false,
+	normalize_negative_zero: true,
+	source_text: None,
+};
+
+
+/// A JSON writer which emits values incrementally without building the tree
+pub struct Serializer<W: Write>
+{
+	writer: W,
+	stack: Vec<Nesting>,
+	has_root: bool,
+}
+
+
+enum Nesting
+{
+	Array { empty: bool },
+	Object { empty: bool, has_key: bool },
+}
+
+
+impl<W: Write> Serializer<W>
+{
+	/// Create a serializer which writes to the writer
+	pub fn new(writer: W) -> Serializer<W>
+	{
+		return Serializer {
+			writer,
+			stack: Vec::new(),
+			has_root: false,
+		};
+	}
+
+	/// Begin an array as the next value
+	pub fn begin_array(&mut self) -> io::Result<()>
+	{
+		self.begin_value()?;
+		self.stack.push(Nesting::Array { empty: true });
+		return self.writer.write_all(b"[");
+	}
+
+	/// End the innermost array
+	pub fn end_array(&mut self) -> io::Result<()>
+	{
+		return match self.stack.last() {
+			Some(Nesting::Array { .. }) => {
+				self.stack.pop();
+				self.writer.write_all(b"]")
+			},
+			_ => Err(misuse("end_array outside of an array")),
+		};
+	}
+
+	/// Begin an object as the next value
+	pub fn begin_object(&mut self) -> io::Result<()>
+	{
+		self.begin_value()?;
+		self.stack.push(Nesting::Object { empty: true, has_key: false });
+		return self.writer.write_all(b"{");
+	}
+
+	/// End the innermost object
+	pub fn end_object(&mut self) -> io::Result<()>
+	{
+		return match self.stack.last() {
+			Some(Nesting::Object { has_key: false, .. }) => {
+				self.stack.pop();
+				self.writer.write_all(b"}")
+			},
+			Some(Nesting::Object { has_key: true, .. }) => Err(misuse("end_object after a key without a value")),
+			_ => Err(misuse("end_object outside of an object")),
+		};
+	}
+
+	/// Write the key of the next object member
+	pub fn key(&mut self, key: &str) -> io::Result<()>
+	{
+		let mut output = String::new();
+		match self.stack.last_mut() {
+			Some(Nesting::Object { empty, has_key: has_key @ false }) => {
+				if !*empty {
+					output.push(',');
+				}
+				*empty = false;
+				*has_key = true;
+			},
+			Some(Nesting::Object { has_key: true, .. }) => return Err(misuse("key after a key without a value")),
+			_ => return Err(misuse("key outside of an object")),
+		}
+		serialize_string(key, &mut output, &SerializeOptions::default());
+		output.push(':');
+		return self.writer.write_all(output.as_bytes());
+	}
+
+	/// Write an entire JSON value as the next value
+	pub fn value(&mut self, value: &Json) -> io::Result<()>
+	{
+		self.begin_value()?;
+		let mut output = String::new();
+		if let Err(error) = serialize(value, &mut output, &SerializeOptions::default()) {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+		}
+		return self.writer.write_all(output.as_bytes());
+	}
+
+	/// Check that exactly one complete value was written and get the writer
+	pub fn finish(self) -> io::Result<W>
+	{
+		if !self.has_root || !self.stack.is_empty() {
+			return Err(misuse("finish before the value is complete"));
+		}
+		return Ok(self.writer);
+	}
+
+	/// Write the separator before a value, or fail if a value isn't expected
+	fn begin_value(&mut self) -> io::Result<()>
+	{
+		match self.stack.last_mut() {
+			None => match self.has_root {
+				true => return Err(misuse("more than one root value")),
+				false => self.has_root = true,
+			},
+			Some(Nesting::Array { empty }) => {
+				if !*empty {
+					self.writer.write_all(b",")?;
+				}
+				*empty = false;
+			},
+			Some(Nesting::Object { has_key: has_key @ true, .. }) => *has_key = false,
+			Some(Nesting::Object { has_key: false, .. }) => return Err(misuse("value in an object without a key")),
+		}
+		return Ok(());
+	}
+}
+
+
+/// Create the error for calling the serializer methods out of order
+fn misuse(message: &'static str) -> io::Error
+{
+	return io::Error::new(io::ErrorKind::InvalidInput, message);
+}
+
+
+/// Serialize the JSON value in linear time without recursion
+pub(crate) fn serialize(root: &Json, output: &mut String, options: &SerializeOptions) -> Result<(), SerializeError>
+{
+	if let Some(max_width) = options.max_width {
+		return serialize_wrapped(root, output, options, max_width);
+	}
+
+	enum State<'a>
+	{
+		Array(core::slice::Iter<'a, Json>, core::slice::Iter<'a, SourceText>, bool),
+		Object(btree_map::Iter<'a, String, Json>, &'a SourceText, bool),
+	}
+
+	let mut stack = Vec::<State>::new();
+	let mut next = Some((root, options.source_text.unwrap_or(&SourceText::None)));
+
+	loop {
+		// Write the scalar or the beginning of the container
+		if let Some((value, text)) = next.take() {
+			match value {
+				Json::Array(array) => {
+					output.push('[');
+					stack.push(State::Array(array.iter(), text.elements(), true));
+				},
+				Json::Boolean(true) => output.push_str("true"),
+				Json::Boolean(false) => output.push_str("false"),
+				Json::Null => output.push_str("null"),
+				Json::Number(number) => match text.scalar(value, options) {
+					Some(text) => output.push_str(text),
+					None => serialize_number(*number, output, options)?,
+				},
+				Json::Object(object) => {
+					output.push('{');
+					stack.push(State::Object(object.iter(), text, true));
+				},
+				Json::String(string) => match text.scalar(value, options) {
+					Some(text) => output.push_str(text),
+					None => serialize_string(string, output, options),
+				},
+			}
+		}
+
+		// Find the next value, otherwise write the end of the container
+		match stack.last_mut() {
+			None => return Ok(()),
+			Some(State::Array(elements, texts, first)) => match elements.next() {
+				Some(element) => {
+					if !*first {
+						output.push(',');
+					}
+					*first = false;
+					next = Some((element, texts.next().unwrap_or(&SourceText::None)));
+				},
+				None => {
+					output.push(']');
+					stack.pop();
+				},
+			},
+			Some(State::Object(members, text, first)) => match members.next() {
+				Some((key, member)) => {
+					if !*first {
+						output.push(',');
+					}
+					*first = false;
+					let (key_text, member_text) = text.member(key);
+					match key_text {
+						Some(key_text) => output.push_str(key_text),
+						None => serialize_string(key, output, options),
+					}
+					output.push(':');
+					next = Some((member, member_text));
+				},
+				None => {
+					output.push('}');
+					stack.pop();
+				},
+			},
+		}
+	}
+}
+
+
+/// Serialize the JSON value on multiple lines without recursion, but keep each
+/// container on one line if it fits within the maximum width
+fn serialize_wrapped(root: &Json, output: &mut String, options: &SerializeOptions, max_width: usize) -> Result<(), SerializeError>
+{
+	enum State<'a>
+	{
+		Array(core::slice::Iter<'a, Json>, core::slice::Iter<'a, SourceText>, bool, bool),
+		Object(btree_map::Iter<'a, String, Json>, &'a SourceText, bool, bool),
+	}
+
+	const INDENT: &str = "  ";
+
+	let mut stack = Vec::<State>::new();
+	let mut next = Some((root, options.source_text.unwrap_or(&SourceText::None)));
+	let mut expand = false;
+	let mut line_start = 0;
+	// The container being tried on one line, with where it begins in the
+	// output and the stack, and the width it may still use
+	let mut attempt: Option<(&Json, &SourceText, usize, usize, usize)> = None;
+	// The length of the output whose characters are counted in the width
+	let mut counted_len = 0;
+
+	loop {
+		// Try the container again on multiple lines if it doesn't fit
+		if let Some((value, text, start, depth, width)) = attempt {
+			let written = output[counted_len..output.len()].chars().count();
+			counted_len = output.len();
+			if written > width {
+				output.truncate(start);
+				stack.truncate(depth);
+				next = Some((value, text));
+				expand = true;
+				attempt = None;
+			}
+			else if stack.len() == depth {
+				attempt = None;
+			}
+			else {
+				attempt = Some((value, text, start, depth, width - written));
+			}
+		}
+
+		// Write the scalar or the beginning of the container
+		if let Some((value, text)) = next.take() {
+			let is_container = matches!(value, Json::Array(_) | Json::Object(_));
+			if attempt.is_none() && is_container && !expand {
+				let has_comma = match stack.last() {
+					None => false,
+					Some(State::Array(elements, _, _, _)) => elements.len() > 0,
+					Some(State::Object(members, _, _, _)) => members.len() > 0,
+				};
+				let column = output[line_start..output.len()].chars().count() + has_comma as usize;
+				attempt = Some((value, text, output.len(), stack.len(), max_width.saturating_sub(column)));
+				counted_len = output.len();
+			}
+			let inline = attempt.is_some();
+			expand = false;
+			match value {
+				Json::Array(array) => {
+					output.push('[');
+					stack.push(State::Array(array.iter(), text.elements(), true, inline));
+				},
+				Json::Boolean(true) => output.push_str("true"),
+				Json::Boolean(false) => output.push_str("false"),
+				Json::Null => output.push_str("null"),
+				Json::Number(number) => match text.scalar(value, options) {
+					Some(text) => output.push_str(text),
+					None => serialize_number(*number, output, options)?,
+				},
+				Json::Object(object) => {
+					output.push('{');
+					stack.push(State::Object(object.iter(), text, true, inline));
+				},
+				Json::String(string) => match text.scalar(value, options) {
+					Some(text) => output.push_str(text),
+					None => serialize_string(string, output, options),
+				},
+			}
+		}
+
+		// Find the next value, otherwise write the end of the container
+		let depth = stack.len();
+		match stack.last_mut() {
+			None => return Ok(()),
+			Some(State::Array(elements, texts, first, inline)) => match elements.next() {
+				Some(element) => {
+					write_separator(output, &mut line_start, *first, *inline, depth, INDENT);
+					*first = false;
+					next = Some((element, texts.next().unwrap_or(&SourceText::None)));
+				},
+				None => {
+					if !*first && !*inline {
+						write_line_break(output, &mut line_start, depth - 1, INDENT);
+					}
+					output.push(']');
+					stack.pop();
+				},
+			},
+			Some(State::Object(members, text, first, inline)) => match members.next() {
+				Some((key, member)) => {
+					write_separator(output, &mut line_start, *first, *inline, depth, INDENT);
+					*first = false;
+					let (key_text, member_text) = text.member(key);
+					match key_text {
+						Some(key_text) => output.push_str(key_text),
+						None => serialize_string(key, output, options),
+					}
+					output.push_str(": ");
+					next = Some((member, member_text));
+				},
+				None => {
+					if !*first && !*inline {
+						write_line_break(output, &mut line_start, depth - 1, INDENT);
+					}
+					output.push('}');
+					stack.pop();
+				},
+			},
+		}
+	}
+}
+
+
+/// Write the separator before an element or member, which is a comma and
+/// either a space or a line break
+fn write_separator(output: &mut String, line_start: &mut usize, first: bool, inline: bool, depth: usize, indent: &str)
+{
+	if !first {
+		output.push(',');
+		if inline {
+			output.push(' ');
+		}
+	}
+	if !inline {
+		write_line_break(output, line_start, depth, indent);
+	}
+}
+
+
+/// Write a line break and the indentation for the depth
+fn write_line_break(output: &mut String, line_start: &mut usize, depth: usize, indent: &str)
+{
+	output.push('\n');
+	*line_start = output.len();
+	for _ in 0..depth {
+		output.push_str(indent);
+	}
+}
+
+
+/// Serialize the number in the notation of the options
+fn serialize_number(number: f64, output: &mut String, options: &SerializeOptions) -> Result<(), SerializeError>
+{
+	let number = match options.normalize_negative_zero && number == 0.0 {
+		true => 0.0,
+		false => number,
+	};
+
+	if !number.is_finite() {
+		match options.non_finite {
+			NonFinitePolicy::Error => return Err(SerializeError::NonFinite),
+			NonFinitePolicy::Null => output.push_str("null"),
+			NonFinitePolicy::AllowLiteral if number.is_nan() => output.push_str("NaN"),
+			NonFinitePolicy::AllowLiteral if number > 0.0 => output.push_str("Infinity"),
+			NonFinitePolicy::AllowLiteral => output.push_str("-Infinity"),
+		}
+		return Ok(());
+	}
+
+	if options.integer_floats && number.fract() == 0.0 && number >= i64::MIN as f64 && number < i64::MAX as f64 {
+		let _ = write!(output, "{}", number as i64);
+		return Ok(());
+	}
+
+	let start = output.len();
+	match options.number_format {
+		NumberFormat::Shortest => {
+			// Both formats use the shortest digits which round-trip, but the
+			// decimal format writes every zero of a large or small exponent
+			let _ = write!(output, "{}", number);
+			let decimal_len = output.len() - start;
+			let mut exponent = String::new();
+			let _ = write!(exponent, "{:e}", number);
+			if exponent.len() < decimal_len {
+				output.truncate(start);
+				output.push_str(&exponent);
+			}
+		},
+		NumberFormat::AlwaysDecimal => {
+			let _ = write!(output, "{}", number);
+			if number.is_finite() && !output[start..output.len()].contains('.') {
+				output.push_str(".0");
+			}
+		},
+		NumberFormat::FixedDecimals(decimals) => {
+			let _ = write!(output, "{:.*}", decimals as usize, number);
+		},
+	}
+	return Ok(());
+}
+
+
+/// Estimate the bytes of the serialization from the size of the leaves, which
+/// is cheaper than `serialized_len` since it doesn't format the numbers
+fn estimated_len(root: &Json) -> usize
+{
+	let mut len = 0;
+	let mut stack = vec![root];
+	while let Some(value) = stack.pop() {
+		len += match value {
+			Json::Array(array) => {
+				stack.extend(array);
+				1 + array.len()
+			},
+			Json::Boolean(_) | Json::Null => 5,
+			Json::Number(_) => core::mem::size_of::<f64>(),
+			Json::Object(object) => {
+				for key in object.keys() {
+					len += key.len() + 3;
+				}
+				stack.extend(object.values());
+				1 + object.len()
+			},
+			Json::String(string) => string.len() + 2,
+		};
+	}
+	return len;
+}
+
+
+/// Count the bytes of the string with quotes and escape sequences
+fn serialized_string_len(string: &str) -> usize
+{
+	let mut len = 2;
+	for c in string.chars() {
+		len += match c {
+			'"' | '\\' | '\u{8}' | '\u{c}' | '\n' | '\r' | '\t' => 2,
+			'\u{0}' ..= '\u{1f}' => 6,
+			c => c.len_utf8(),
+		};
+	}
+	return len;
+}
+
+
+/// Serialize the string with quotes and escape sequences
+pub(crate) fn serialize_string(string: &str, output: &mut String, options: &SerializeOptions)
+{
+	output.push('"');
+	for c in string.chars() {
+		match c {
+			'"' => output.push_str("\\\""),
+			'\\' => output.push_str("\\\\"),
+			'/' if options.escape_forward_slash => output.push_str("\\/"),
+			'\u{8}' => output.push_str("\\b"),
+			'\u{c}' => output.push_str("\\f"),
+			'\n' => output.push_str("\\n"),
+			'\r' => output.push_str("\\r"),
+			'\t' => output.push_str("\\t"),
+			'\u{0}' ..= '\u{1f}' => {
+				let _ = write!(output, "\\u{:04x}", c as u32);
+			},
+			c => output.push(c),
+		}
+	}
+	output.push('"');
+}
+
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use std::collections::hash_map::DefaultHasher;
+	use std::collections::BTreeMap;
+
+	/// Hash the JSON data with `hash_stream`
+	fn stream_hash(bytes: &[u8]) -> u64
+	{
+		let mut hasher = DefaultHasher::new();
+		Json::hash_stream(bytes, &mut hasher).unwrap();
+		return hasher.finish();
+	}
+
+	/// Hash the canonical bytes like `hash_stream` does
+	fn bytes_hash(value: &Json) -> u64
+	{
+		let mut hasher = DefaultHasher::new();
+		hasher.write(&value.to_hash_bytes());
+		return hasher.finish();
+	}
+
+	#[test]
+	fn hash_bytes_normalize_negative_zero()
+	{
+		assert_eq!(Json::Number(-0.0).to_hash_bytes(), b"0");
+		assert_eq!(Json::Number(-0.0).to_bytes(), b"-0");
+		assert_eq!(stream_hash(b"[-0, -0.0]"), stream_hash(b"[0, 0]"));
+		assert_eq!(stream_hash(b"[-0]"), bytes_hash(&Json::Array(vec![Json::Number(0.0)])));
+	}
+
+	#[test]
+	fn normalize_negative_zero_option()
+	{
+		let options = SerializeOptions { normalize_negative_zero: true, ..SerializeOptions::default() };
+		assert_eq!(Json::Number(-0.0).to_string_with(&options).unwrap(), "0");
+		assert_eq!(Json::Number(-1.5).to_string_with(&options).unwrap(), "-1.5");
+	}
+
+	#[test]
+	fn fingerprint_of_equal_values()
+	{
+		assert_eq!(Json::Number(0.0).fingerprint(), Json::Number(-0.0).fingerprint());
+		let a = Json::parse(b"{\"b\": [1, -0], \"a\": null}").unwrap();
+		let b = Json::parse(b"{ \"a\": null, \"b\": [1.0, 0] }").unwrap();
+		assert_eq!(a.fingerprint(), b.fingerprint());
+		assert_ne!(a.fingerprint(), Json::Null.fingerprint());
+		assert_eq!(Json::Null.fingerprint().len(), 16);
+	}
+
+	#[test]
+	fn serialized_len_matches_to_string()
+	{
+		let value = Json::parse(b"{\"a\\n\": [1.5, -0, 1e300, true, null], \"b\": \"\\u0001\xc3\xa9\"}").unwrap();
+		assert_eq!(value.serialized_len(), value.to_string().len());
+		assert_eq!(value.to_bytes(), value.to_string().into_bytes());
+	}
+
+	#[test]
+	fn hash_stream_matches_hash_bytes()
+	{
+		let documents: [&[u8]; 6] = [
+			b"null",
+			b" [1, [], {}, \"a\\u0041\", true] ",
+			b"{\"b\": {\"d\": [1, {\"f\": 2, \"e\": 3}], \"c\": null}, \"a\": [[[]]]}",
+			b"[{\"z\": 1, \"y\": {\"x\": [2, 3]}}, {\"w\": 4}]",
+			b"{\"long\": \"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx\"}",
+			b"[1e2, 0.5, -3]",
+		];
+		for bytes in documents {
+			let value = Json::parse_detailed(bytes).unwrap();
+			assert_eq!(stream_hash(bytes), bytes_hash(&value));
+		}
+	}
+
+	#[test]
+	fn hash_stream_errors()
+	{
+		let documents: [&[u8]; 10] = [
+			b"",
+			b"[1 2]",
+			b"[1,]",
+			b"{\"a\": 1,}",
+			b"{\"a\" 1}",
+			b"{\"a\": [1 2]}",
+			b"{\"a\": [1}",
+			b"[1] 2",
+			b"{\"a\": 1, \"a\": 2}",
+			b"[\"\x01\"]",
+		];
+		for bytes in documents {
+			let mut hasher = DefaultHasher::new();
+			assert!(Json::parse_detailed(bytes).is_err());
+			assert!(Json::hash_stream(bytes, &mut hasher).is_err());
+		}
+		let mut hasher = DefaultHasher::new();
+		assert_eq!(
+			Json::hash_stream(b"[1] 2", &mut hasher),
+			Err(ParseError { kind: ParseErrorKind::TrailingData, offset: 4 })
+		);
+		assert_eq!(
+			Json::hash_stream(b"{\"a\": 1, \"a\": 2}", &mut hasher),
+			Err(Json::parse_detailed(b"{\"a\": 1, \"a\": 2}").err().unwrap())
+		);
+	}
+
+	#[test]
+	fn serializer_writes_incrementally()
+	{
+		let mut serializer = Serializer::new(Vec::new());
+		serializer.begin_object().unwrap();
+		serializer.key("a").unwrap();
+		serializer.begin_array().unwrap();
+		serializer.value(&Json::Number(1.0)).unwrap();
+		serializer.value(&Json::String("x\"".to_string())).unwrap();
+		serializer.begin_object().unwrap();
+		serializer.end_object().unwrap();
+		serializer.end_array().unwrap();
+		serializer.key("b").unwrap();
+		serializer.value(&Json::Null).unwrap();
+		serializer.end_object().unwrap();
+
+		let array = vec![Json::Number(1.0), Json::String("x\"".to_string()), Json::Object(BTreeMap::new())];
+		let value = Json::Object(BTreeMap::from([("a".to_string(), Json::Array(array)), ("b".to_string(), Json::Null)]));
+		assert_eq!(serializer.finish().unwrap(), value.to_string().into_bytes());
+	}
+
+	#[test]
+	fn serializer_misuse()
+	{
+		let mut serializer = Serializer::new(Vec::new());
+		assert!(serializer.end_array().is_err());
+		assert!(serializer.key("a").is_err());
+		serializer.begin_object().unwrap();
+		assert!(serializer.value(&Json::Null).is_err());
+		serializer.key("a").unwrap();
+		assert!(serializer.key("b").is_err());
+		assert!(serializer.end_object().is_err());
+		serializer.value(&Json::Null).unwrap();
+		assert!(serializer.end_array().is_err());
+		serializer.end_object().unwrap();
+		assert!(serializer.value(&Json::Null).is_err());
+		assert!(Serializer::new(Vec::new()).finish().is_err());
+		let mut serializer = Serializer::new(Vec::new());
+		serializer.begin_array().unwrap();
+		assert!(serializer.finish().is_err());
+	}
+
+	/// Get the next pseudorandom number of the xorshift generator
+	fn next_random(state: &mut u64) -> u64
+	{
+		*state ^= *state << 13;
+		*state ^= *state >> 7;
+		*state ^= *state << 17;
+		return *state;
+	}
+
+	#[test]
+	fn shortest_numbers_round_trip()
+	{
+		let mut state = 0x9e3779b97f4a7c15;
+		let mut count = 0;
+		while count < 100_000 {
+			let number = f64::from_bits(next_random(&mut state));
+			if !number.is_finite() {
+				continue;
+			}
+			count += 1;
+			let serialized = Json::Number(number).to_string();
+			assert_eq!(Json::parse_number(serialized.as_bytes()).map(f64::to_bits), Some(number.to_bits()), "{}", serialized);
+			assert!(serialized.len() <= format!("{}", number).len().min(format!("{:e}", number).len()));
+		}
+	}
+
+	#[test]
+	fn shortest_numbers_round_trip_decimals()
+	{
+		let mut state = 0x2545f4914f6cdd1d;
+		for _ in 0..100_000 {
+			let digits = next_random(&mut state) % 10_000_000;
+			let exponent = (next_random(&mut state) % 40) as i32 - 20;
+			let number = digits as f64 * 10f64.powi(exponent);
+			let serialized = Json::Number(number).to_string();
+			assert_eq!(Json::parse_number(serialized.as_bytes()).map(f64::to_bits), Some(number.to_bits()), "{}", serialized);
+		}
+	}
+
+	#[test]
+	fn shortest_number_notation()
+	{
+		let numbers = [(1.0, "1"), (0.5, "0.5"), (1e-7, "1e-7"), (1e21, "1e21"), (123456.0, "123456"), (-0.0, "-0"), (0.1 + 0.2, "0.30000000000000004")];
+		for (number, expected) in numbers {
+			assert_eq!(Json::Number(number).to_string(), expected);
+		}
+	}
+
+	#[test]
+	fn hash_stream_deep_nesting()
+	{
+		let depth = 100_000;
+		let bytes = format!("{}{}", "{\"b\": 1, \"a\": [".repeat(depth), "]}".repeat(depth));
+		let value = Json::parse_detailed(bytes.as_bytes()).unwrap();
+		assert_eq!(stream_hash(bytes.as_bytes()), bytes_hash(&value));
+	}
+
+	#[test]
+	fn escape_forward_slash()
+	{
+		let value = Json::String("</script>\u{1}\n\"\\".to_string());
+		assert_eq!(value.to_string(), "\"</script>\\u0001\\n\\\"\\\\\"");
+		let options = SerializeOptions { escape_forward_slash: true, ..SerializeOptions::default() };
+		assert_eq!(value.to_string_with(&options).unwrap(), "\"<\\/script>\\u0001\\n\\\"\\\\\"");
+		let value = Json::parse(b"{\"a/b\": \"/\"}").unwrap();
+		assert_eq!(value.to_string_with(&options).unwrap(), "{\"a\\/b\":\"\\/\"}");
+	}
+
+	#[test]
+	fn max_width_wrapping()
+	{
+		let value = Json::parse(b"{\"a\": [1, 2, 3], \"b\": {\"c\": \"long string value\", \"d\": []}, \"e\": {}}").unwrap();
+		let wrap = |max_width: usize| value.to_string_with(&SerializeOptions { max_width: Some(max_width), ..SerializeOptions::default() }).unwrap();
+		assert_eq!(wrap(100), "{\"a\": [1, 2, 3], \"b\": {\"c\": \"long string value\", \"d\": []}, \"e\": {}}");
+		assert_eq!(wrap(45), "{\n  \"a\": [1, 2, 3],\n  \"b\": {\"c\": \"long string value\", \"d\": []},\n  \"e\": {}\n}");
+		assert_eq!(wrap(20), "{\n  \"a\": [1, 2, 3],\n  \"b\": {\n    \"c\": \"long string value\",\n    \"d\": []\n  },\n  \"e\": {}\n}");
+		assert_eq!(wrap(0), "{\n  \"a\": [\n    1,\n    2,\n    3\n  ],\n  \"b\": {\n    \"c\": \"long string value\",\n    \"d\": []\n  },\n  \"e\": {}\n}");
+		for max_width in 0..80 {
+			assert!(Json::parse(wrap(max_width).as_bytes()).is_some_and(|parsed| parsed == value));
+		}
+	}
+
+	#[test]
+	fn max_width_counts_characters()
+	{
+		let value = Json::Array(vec![Json::String("ééé".to_string()), Json::String("ééé".to_string())]);
+		let options = SerializeOptions { max_width: Some(14), ..SerializeOptions::default() };
+		assert_eq!(value.to_string_with(&options).unwrap(), "[\"ééé\", \"ééé\"]");
+		let options = SerializeOptions { max_width: Some(13), ..SerializeOptions::default() };
+		assert_eq!(value.to_string_with(&options).unwrap(), "[\n  \"ééé\",\n  \"ééé\"\n]");
+	}
+
+	#[test]
+	fn max_width_of_long_arrays()
+	{
+		let value = Json::Array((0..100_000).map(|i| Json::Number(i as f64)).collect());
+		let options = SerializeOptions { max_width: Some(usize::MAX), ..SerializeOptions::default() };
+		let output = value.to_string_with(&options).unwrap();
+		assert!(!output.contains('\n'));
+		assert!(Json::parse(output.as_bytes()).is_some_and(|parsed| parsed == value));
+	}
+
+	#[test]
+	fn number_formats()
+	{
+		let format = |number: f64, options: SerializeOptions| Json::Number(number).to_string_with(&options).unwrap();
+		let always_decimal = SerializeOptions { number_format: NumberFormat::AlwaysDecimal, ..SerializeOptions::default() };
+		assert_eq!(format(1.0, always_decimal), "1.0");
+		assert_eq!(format(1e-7, always_decimal), "0.0000001");
+		assert_eq!(format(2.5, always_decimal), "2.5");
+		let fixed = SerializeOptions { number_format: NumberFormat::FixedDecimals(2), ..SerializeOptions::default() };
+		assert_eq!(format(1.23456, fixed), "1.23");
+		assert_eq!(format(1.0, fixed), "1.00");
+		assert_eq!(format(1.0, SerializeOptions::default()), "1");
+		assert_eq!(format(1e-7, SerializeOptions::default()), "1e-7");
+	}
+
+	#[test]
+	fn non_finite_policies()
+	{
+		let value = Json::Array(vec![Json::Number(f64::NAN), Json::Number(f64::INFINITY), Json::Number(f64::NEG_INFINITY)]);
+		assert_eq!(value.to_string(), "[null,null,null]");
+		let error = SerializeOptions { non_finite: NonFinitePolicy::Error, ..SerializeOptions::default() };
+		assert_eq!(value.to_string_with(&error), Err(SerializeError::NonFinite));
+		let literal = SerializeOptions { non_finite: NonFinitePolicy::AllowLiteral, ..SerializeOptions::default() };
+		assert_eq!(value.to_string_with(&literal).unwrap(), "[NaN,Infinity,-Infinity]");
+	}
+
+	#[test]
+	fn format_bytes()
+	{
+		let options = SerializeOptions { max_width: Some(0), ..SerializeOptions::default() };
+		assert_eq!(Json::format_bytes(b" [1,{\"a\":null}] ", &options).unwrap(), "[\n  1,\n  {\n    \"a\": null\n  }\n]");
+		assert!(matches!(Json::format_bytes(b"[1,", &options), Err(FormatError::Parse(_))));
+		let error = SerializeOptions { non_finite: NonFinitePolicy::Error, ..SerializeOptions::default() };
+		assert!(Json::format_bytes(b"[1]", &error).is_ok());
+	}
+
+	#[test]
+	fn integer_floats()
+	{
+		let format = |number: f64, options: SerializeOptions| Json::Number(number).to_string_with(&options).unwrap();
+		let always_decimal = SerializeOptions { number_format: NumberFormat::AlwaysDecimal, ..SerializeOptions::default() };
+		let integers = SerializeOptions { integer_floats: true, ..always_decimal };
+		assert_eq!(format(5.0, always_decimal), "5.0");
+		assert_eq!(format(5.0, integers), "5");
+		assert_eq!(format(1e5, integers), "100000");
+		assert_eq!(format(5.5, integers), "5.5");
+		assert_eq!(format(1e300, SerializeOptions { integer_floats: true, ..SerializeOptions::default() }), "1e300");
+	}
+}
@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+
+use crate::parse_and_find;
+use crate::span::token_len;
+use crate::Json;
+use crate::ParseError;
+use crate::ParseOptions;
+use crate::SerializeOptions;
+use crate::Token;
+
+
+/// The source text of the numbers, strings, and keys of a parsed value, with
+/// the same structure as the value
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum SourceText
+{
+	/// A value without any source text
+	#[default]
+	None,
+	Array(Vec<SourceText>),
+	/// The members by key, with the source text of each key if it's kept
+	Object(BTreeMap<String, (Option<String>, SourceText)>),
+	/// The text of a Number or String, like `1e2` or `"\u0041"`
+	Scalar(String),
+}
+
+
+impl SourceText
+{
+	/// Get the source text of the elements, which are none if it isn't an
+	/// Array
+	pub(crate) fn elements(&self) -> core::slice::Iter<'_, SourceText>
+	{
+		return match self {
+			SourceText::Array(texts) => texts.iter(),
+			_ => [].iter(),
+		};
+	}
+
+	/// Get the source text of the key and the value of the member, if it's
+	/// still the same key
+	pub(crate) fn member(&self, key: &str) -> (Option<&str>, &SourceText)
+	{
+		return match self {
+			SourceText::Object(texts) => match texts.get(key) {
+				Some((key_text, text)) => (key_text.as_deref().filter(|key_text| is_same_string(key_text, key)), text),
+				None => (None, &SourceText::None),
+			},
+			_ => (None, &SourceText::None),
+		};
+	}
+
+	/// Get the source text of the Number or String, if it still has the same
+	/// value and the options don't change how it's written
+	pub(crate) fn scalar(&self, value: &Json, options: &SerializeOptions) -> Option<&str>
+	{
+		return match (value, self) {
+			(Json::Number(number), SourceText::Scalar(text)) => match options.normalize_negative_zero && *number == 0.0 && number.is_sign_negative() {
+				true => None,
+				false => Some(text.as_str()).filter(|text| is_same_number(text, *number)),
+			},
+			(Json::String(string), SourceText::Scalar(text)) => Some(text.as_str()).filter(|text| is_same_string(text, string)),
+			_ => None,
+		};
+	}
+}
+
+
+impl Json
+{
+	/// Parse a JSON value with the options and keep the source text of every
+	/// Number if `keep_number_text` is set, and every String and key if
+	/// `keep_string_text` is set, so `to_string_with` can write them with the
+	/// same spelling
+	pub fn parse_with_source_text(bytes: &[u8], options: &ParseOptions) -> Result<(Json, SourceText), ParseError>
+	{
+		return parse_and_find(bytes, options, |tokens, ends| find_source_text(bytes, tokens, ends, options));
+	}
+}
+
+
+/// Check if the source text of a Number is standard JSON with the exact same
+/// value
+fn is_same_number(text: &str, number: f64) -> bool
+{
+	return Json::parse_number(text.as_bytes()).is_some_and(|parsed| parsed.to_bits() == number.to_bits());
+}
+
+
+/// Check if the source text of a String is standard JSON with the exact same
+/// value
+fn is_same_string(text: &str, string: &str) -> bool
+{
+	return Json::parse_string(text.as_bytes()).is_some_and(|parsed| parsed == string);
+}
+
+
+/// Find the source text of the valid tokens without recursion
+fn find_source_text(bytes: &[u8], tokens: &[(usize, Token)], ends: &[usize], options: &ParseOptions) -> SourceText
+{
+	/// A container with its source text so far, the index of the token which
+	/// begins the next element or member, and the index of its last token
+	enum Frame
+	{
+		Array(Vec<SourceText>, usize, usize),
+		Object(BTreeMap<String, (Option<String>, SourceText)>, usize, usize),
+	}
+
+	if tokens.is_empty() {
+		return SourceText::None;
+	}
+
+	let mut stack = Vec::<Frame>::new();
+	let mut i = 0;
+
+	loop {
+		// Begin the container, otherwise keep the source text of the scalar
+		let mut text = match &tokens[i].1 {
+			Token::ArrayBegin => {
+				stack.push(Frame::Array(Vec::new(), i + 1, ends[i]));
+				None
+			},
+			Token::ObjectBegin => {
+				stack.push(Frame::Object(BTreeMap::new(), i + 1, ends[i]));
+				None
+			},
+			Token::Number(_) if options.keep_number_text => Some(SourceText::Scalar(token_text(bytes, &tokens[i], options))),
+			Token::String(_) if options.keep_string_text => Some(SourceText::Scalar(token_text(bytes, &tokens[i], options))),
+			_ => Some(SourceText::None),
+		};
+
+		// Put each complete value into its container until the next value
+		loop {
+			let (cursor, end) = match stack.last_mut() {
+				None => match text {
+					Some(text) => return text,
+					None => unreachable!(),
+				},
+				Some(Frame::Array(texts, cursor, end)) => {
+					if let Some(text) = text.take() {
+						texts.push(text);
+						*cursor = ends[*cursor] + 1;
+					}
+					(cursor, *end)
+				},
+				Some(Frame::Object(texts, cursor, end)) => {
+					if let Some(text) = text.take() {
+						if let Token::Identifier(key) | Token::String(key) = &tokens[*cursor].1 {
+							let key_text = Some(&tokens[*cursor]).filter(|_| options.keep_string_text).map(|token| token_text(bytes, token, options));
+							texts.insert(key.clone(), (key_text, text));
+						}
+						*cursor = ends[*cursor + 2] + 1;
+					}
+					(cursor, *end)
+				},
+			};
+			if let Token::Comma = tokens[*cursor].1 {
+				*cursor += 1;
+			}
+			if *cursor < end {
+				i = *cursor;
+				if let Some(Frame::Object(..)) = stack.last() {
+					i += 2;
+				}
+				break;
+			}
+
+			text = match stack.pop() {
+				Some(Frame::Array(texts, _, _)) => Some(SourceText::Array(texts)),
+				Some(Frame::Object(texts, _, _)) => Some(SourceText::Object(texts)),
+				None => unreachable!(),
+			};
+		}
+	}
+}
+
+
+/// Get the source text of the valid scalar token
+fn token_text(bytes: &[u8], (offset, token): &(usize, Token), options: &ParseOptions) -> String
+{
+	let token_len = token_len(&bytes[*offset..bytes.len()], token, options);
+	return String::from_utf8_lossy(&bytes[*offset..*offset + token_len]).into_owned();
+}
+
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	/// Parse the data while keeping the source text and serialize it again
+	fn round_trip(bytes: &[u8]) -> String
+	{
+		let options = ParseOptions { keep_number_text: true, keep_string_text: true, ..ParseOptions::default() };
+		let (value, text) = Json::parse_with_source_text(bytes, &options).unwrap();
+		return value.to_string_with(&SerializeOptions { source_text: Some(&text), ..SerializeOptions::default() }).unwrap();
+	}
+
+	#[test]
+	fn numbers_keep_their_spelling()
+	{
+		assert_eq!(round_trip(b"1e2"), "1e2");
+		assert_eq!(round_trip(b"1.0"), "1.0");
+		assert_eq!(round_trip(b"100"), "100");
+		assert_eq!(round_trip(b" [1e2, 1.0, 100, -0, 1E+2] "), "[1e2,1.0,100,-0,1E+2]");
+		assert_eq!(round_trip(b"{\"b\": 2.50, \"a\": [0.10], \"c\\\"\": {\"d\": 1e-0}}"), "{\"a\":[0.10],\"b\":2.50,\"c\\\"\":{\"d\":1e-0}}");
+	}
+
+	#[test]
+	fn numbers_lose_their_spelling_unless_kept()
+	{
+		let (value, text) = Json::parse_with_source_text(b"[1e2, 1.0]", &ParseOptions::default()).unwrap();
+		assert!(text == SourceText::Array(vec![SourceText::None, SourceText::None]));
+		assert_eq!(value.to_string_with(&SerializeOptions { source_text: Some(&text), ..SerializeOptions::default() }).unwrap(), "[100,1]");
+	}
+
+	#[test]
+	fn changed_numbers_lose_their_spelling()
+	{
+		let options = ParseOptions { keep_number_text: true, ..ParseOptions::default() };
+		let (mut value, text) = Json::parse_with_source_text(b"[1e2, 2.0, 3.0, -0]", &options).unwrap();
+		if let Json::Array(array) = &mut value {
+			array[1] = Json::Number(5.0);
+			array.push(Json::Number(1e2));
+		}
+		let options = SerializeOptions { source_text: Some(&text), ..SerializeOptions::default() };
+		assert_eq!(value.to_string_with(&options).unwrap(), "[1e2,5,3.0,-0,100]");
+		let options = SerializeOptions { normalize_negative_zero: true, ..options };
+		assert_eq!(value.to_string_with(&options).unwrap(), "[1e2,5,3.0,0,100]");
+		assert_eq!(value.to_string_with(&SerializeOptions::default()).unwrap(), value.to_string());
+	}
+
+	#[test]
+	fn strings_and_keys_keep_their_spelling()
+	{
+		let bytes = b"{\"\\u0041\": \"\\u00e9\\/\\uD83D\\uDE00\", \"b\": [\"\\t\", \"\\u0009\", \"\\\"\"]}";
+		assert_eq!(round_trip(bytes).as_bytes(), bytes.iter().copied().filter(|byte| *byte != b' ').collect::<Vec<u8>>());
+		assert_eq!(round_trip(b"\"\\u0041\""), "\"\\u0041\"");
+	}
+
+	#[test]
+	fn strings_and_keys_lose_their_spelling_unless_kept()
+	{
+		let options = ParseOptions { keep_number_text: true, ..ParseOptions::default() };
+		let (value, text) = Json::parse_with_source_text(b"{\"\\u0061\": [\"\\u0041\", 1.0]}", &options).unwrap();
+		assert!(text == SourceText::Object(BTreeMap::from([("a".to_string(), (None, SourceText::Array(vec![SourceText::None, SourceText::Scalar("1.0".to_string())])))])));
+		assert_eq!(value.to_string_with(&SerializeOptions { source_text: Some(&text), ..SerializeOptions::default() }).unwrap(), "{\"a\":[\"A\",1.0]}");
+	}
+
+	#[test]
+	fn changed_strings_lose_their_spelling()
+	{
+		let options = ParseOptions { keep_string_text: true, ..ParseOptions::default() };
+		let (mut value, text) = Json::parse_with_source_text(b"{\"\\u0061\": \"\\u0041\", \"b\": \"\\u0042\"}", &options).unwrap();
+		if let Json::Object(object) = &mut value {
+			object.insert("b".to_string(), Json::String("C".to_string()));
+			object.insert("c".to_string(), Json::String("\u{e9}".to_string()));
+		}
+		let options = SerializeOptions { source_text: Some(&text), ..SerializeOptions::default() };
+		assert_eq!(value.to_string_with(&options).unwrap(), "{\"\\u0061\":\"\\u0041\",\"b\":\"C\",\"c\":\"\u{e9}\"}");
+	}
+
+	#[test]
+	fn source_text_with_max_width()
+	{
+		let options = ParseOptions { keep_number_text: true, ..ParseOptions::default() };
+		let (value, text) = Json::parse_with_source_text(b"{\"a\": [1.0, 2.0, 3.0], \"b\": 1e2}", &options).unwrap();
+		let options = SerializeOptions { max_width: Some(24), source_text: Some(&text), ..SerializeOptions::default() };
+		assert_eq!(value.to_string_with(&options).unwrap(), "{\n  \"a\": [1.0, 2.0, 3.0],\n  \"b\": 1e2\n}");
+		let options = SerializeOptions { max_width: Some(80), ..options };
+		assert_eq!(value.to_string_with(&options).unwrap(), "{\"a\": [1.0, 2.0, 3.0], \"b\": 1e2}");
+
+		let options = ParseOptions { keep_string_text: true, ..ParseOptions::default() };
+		let (value, text) = Json::parse_with_source_text(b"{\"a\": [1.0, 2.0, 3.0], \"b\": \"\\u0041\"}", &options).unwrap();
+		let options = SerializeOptions { max_width: Some(30), source_text: Some(&text), ..SerializeOptions::default() };
+		assert_eq!(value.to_string_with(&options).unwrap(), "{\n  \"a\": [1, 2, 3],\n  \"b\": \"\\u0041\"\n}");
+		let options = SerializeOptions { source_text: None, ..options };
+		assert_eq!(value.to_string_with(&options).unwrap(), "{\"a\": [1, 2, 3], \"b\": \"A\"}");
+	}
+
+	#[test]
+	fn parse_options()
+	{
+		let options = ParseOptions { keep_number_text: true, ..ParseOptions::json5() };
+		let (value, text) = Json::parse_with_source_text(b"{key: [0x10, 1.50, +1, NaN,], // comment\n 'b': 2e0,}", &options).unwrap();
+		let serialize_options = SerializeOptions { source_text: Some(&text), ..SerializeOptions::default() };
+		assert_eq!(value.to_string_with(&serialize_options).unwrap(), "{\"b\":2e0,\"key\":[16,1.50,1,null]}");
+		assert!(Json::parse_with_source_text(b"[1e2,]", &ParseOptions::default()).err() == Some(ParseError { kind: crate::ParseErrorKind::UnexpectedToken, offset: 5 }));
+		let options = ParseOptions { max_number_abs: Some(10.0), ..options };
+		assert!(Json::parse_with_source_text(b"[1e2]", &options).err() == Json::parse_with(b"[1e2]", &options).err());
+		let options = ParseOptions { empty_as_null: true, ..options };
+		assert!(Json::parse_with_source_text(b" ", &options).unwrap().1 == SourceText::None);
+	}
+}
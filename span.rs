@@ -0,0 +1,189 @@
+use core::ops::Range;
+use std::collections::BTreeMap;
+
+use crate::parse_and_find;
+use crate::peek_identifier;
+use crate::peek_number;
+use crate::Json;
+use crate::ParseError;
+use crate::ParseOptions;
+use crate::Token;
+
+
+/// The byte range of a parsed value in the data, with the spans of the array
+/// elements or object members
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Span
+{
+	Array(Range<usize>, Vec<Span>),
+	Object(Range<usize>, BTreeMap<String, Span>),
+	Scalar(Range<usize>),
+}
+
+
+impl Span
+{
+	/// Get the byte range of the value, including the brackets of a container
+	pub fn range(&self) -> Range<usize>
+	{
+		return match self {
+			Span::Array(range, _) | Span::Object(range, _) | Span::Scalar(range) => range.clone(),
+		};
+	}
+}
+
+
+impl Json
+{
+	/// Parse a JSON value with the options and get the byte span of every
+	/// value inside it with the same structure, like for pointing to the source
+	/// of a value. The span of empty data which is Null is empty.
+	pub fn parse_with_spans(bytes: &[u8], options: &ParseOptions) -> Result<(Json, Span), ParseError>
+	{
+		return parse_and_find(bytes, options, |tokens, ends| find_spans(bytes, tokens, ends, options));
+	}
+}
+
+
+/// Find the spans of the valid tokens without recursion
+fn find_spans(bytes: &[u8], tokens: &[(usize, Token)], ends: &[usize], options: &ParseOptions) -> Span
+{
+	/// A container with its spans so far, the index of the token which begins
+	/// the next element or member, and the index of its last token
+	enum Frame
+	{
+		Array(usize, Vec<Span>, usize, usize),
+		Object(usize, BTreeMap<String, Span>, usize, usize),
+	}
+
+	if tokens.is_empty() {
+		return Span::Scalar(0..0);
+	}
+
+	let mut stack = Vec::<Frame>::new();
+	let mut i = 0;
+
+	loop {
+		// Begin the container, otherwise complete the scalar
+		let (offset, token) = &tokens[i];
+		let mut span = match token {
+			Token::ArrayBegin => {
+				stack.push(Frame::Array(*offset, Vec::new(), i + 1, ends[i]));
+				None
+			},
+			Token::ObjectBegin => {
+				stack.push(Frame::Object(*offset, BTreeMap::new(), i + 1, ends[i]));
+				None
+			},
+			_ => Some(Span::Scalar(*offset..*offset + token_len(&bytes[*offset..bytes.len()], token, options))),
+		};
+
+		// Put each complete value into its container until the next value
+		loop {
+			let (cursor, end) = match stack.last_mut() {
+				None => match span {
+					Some(span) => return span,
+					None => unreachable!(),
+				},
+				Some(Frame::Array(_, spans, cursor, end)) => {
+					if let Some(span) = span.take() {
+						spans.push(span);
+						*cursor = ends[*cursor] + 1;
+					}
+					(cursor, *end)
+				},
+				Some(Frame::Object(_, spans, cursor, end)) => {
+					if let Some(span) = span.take() {
+						if let Token::Identifier(key) | Token::String(key) = &tokens[*cursor].1 {
+							spans.insert(key.clone(), span);
+						}
+						*cursor = ends[*cursor + 2] + 1;
+					}
+					(cursor, *end)
+				},
+			};
+			if let Token::Comma = tokens[*cursor].1 {
+				*cursor += 1;
+			}
+			if *cursor < end {
+				i = *cursor;
+				if let Some(Frame::Object(..)) = stack.last() {
+					i += 2;
+				}
+				break;
+			}
+
+			let range_end = tokens[end].0 + 1;
+			span = match stack.pop() {
+				Some(Frame::Array(start, spans, _, _)) => Some(Span::Array(start..range_end, spans)),
+				Some(Frame::Object(start, spans, _, _)) => Some(Span::Object(start..range_end, spans)),
+				None => unreachable!(),
+			};
+		}
+	}
+}
+
+
+/// Find the length of the valid scalar token at the start
+pub(crate) fn token_len(remaining_bytes: &[u8], token: &Token, options: &ParseOptions) -> usize
+{
+	return match token {
+		Token::Boolean(true) | Token::Null => 4,
+		Token::Boolean(false) => 5,
+		Token::Identifier(_) => peek_identifier(remaining_bytes),
+		Token::Number(_) => peek_number(remaining_bytes, options).0,
+		Token::String(_) => {
+			let quote = remaining_bytes[0];
+			let mut i = 1;
+			while remaining_bytes[i] != quote {
+				i += match remaining_bytes[i] {
+					b'\\' => 2,
+					_ => 1,
+				};
+			}
+			i + 1
+		},
+		_ => 1,
+	};
+}
+
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	#[test]
+	fn parse_options()
+	{
+		let options = ParseOptions::json5();
+		let (value, span) = Json::parse_with_spans(b"{key: [0x10, 'a',], /* comment */ b: +1}", &options).unwrap();
+		assert_eq!(value.to_string(), "{\"b\":1,\"key\":[16,\"a\"]}");
+		let expected = Span::Object(0..40, BTreeMap::from([
+			("b".to_string(), Span::Scalar(37..39)),
+			("key".to_string(), Span::Array(6..18, vec![Span::Scalar(7..11), Span::Scalar(13..16)])),
+		]));
+		assert_eq!(span, expected);
+		assert!(Json::parse_with_spans(b"[1,]", &ParseOptions::default()).is_err());
+		let options = ParseOptions { max_tokens: Some(2), ..options };
+		assert!(Json::parse_with_spans(b"[1]", &options).err() == Json::parse_with(b"[1]", &options).err());
+		let options = ParseOptions { empty_as_null: true, ..options };
+		assert_eq!(Json::parse_with_spans(b"", &options).unwrap().1, Span::Scalar(0..0));
+	}
+
+	#[test]
+	fn spans_of_nested_values()
+	{
+		let bytes = b" {\"a\": [1, true], \"b\": {\"c\": \"x\"}} ";
+		let (value, span) = Json::parse_with_spans(bytes, &ParseOptions::default()).unwrap();
+		assert_eq!(value.to_string(), "{\"a\":[1,true],\"b\":{\"c\":\"x\"}}");
+		assert_eq!(span.range(), 1..bytes.len() - 1);
+		let members = match &span {
+			Span::Object(_, members) => members,
+			_ => panic!("{:?}", span),
+		};
+		assert_eq!(members["a"], Span::Array(7..16, vec![Span::Scalar(8..9), Span::Scalar(11..15)]));
+		assert_eq!(&bytes[members["b"].range()], b"{\"c\": \"x\"}");
+		assert!(Json::parse_with_spans(b"[1, 2", &ParseOptions::default()).is_err());
+	}
+}
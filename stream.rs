@@ -0,0 +1,446 @@
+use std::io;
+use std::io::Read;
+
+use crate::Json;
+use crate::ParseError;
+use crate::ParseErrorKind;
+
+
+impl Json
+{
+	/// Parse a root Array from the reader and get each element as soon as it's
+	/// parsed, so the entire Array is never in memory. The data after the
+	/// Array is checked after the last element. The iterator ends after the
+	/// first error, where the offset is from the start of the data.
+	pub fn stream_array<R: Read>(reader: R) -> impl Iterator<Item = Result<Json, ParseError>>
+	{
+		return ArrayStream {
+			reader,
+			buffer: Vec::new(),
+			start: 0,
+			buffer_offset: 0,
+			state: StreamState::Begin,
+		};
+	}
+}
+
+
+/// The elements of the root Array being read
+struct ArrayStream<R: Read>
+{
+	reader: R,
+	buffer: Vec<u8>,
+	/// The index of the first byte in the buffer which isn't parsed
+	start: usize,
+	/// The offset in the data of the first byte in the buffer
+	buffer_offset: usize,
+	state: StreamState,
+}
+
+
+enum StreamState
+{
+	Begin,
+	Elements,
+	/// The Array ended, but the data after it isn't checked yet
+	Finish,
+	End,
+}
+
+
+impl<R: Read> Iterator for ArrayStream<R>
+{
+	type Item = Result<Json, ParseError>;
+
+	fn next(&mut self) -> Option<Result<Json, ParseError>>
+	{
+		let result = self.next_element();
+		if !matches!(result, Ok(Some(_))) {
+			self.state = StreamState::End;
+		}
+		return result.transpose();
+	}
+}
+
+
+impl<R: Read> ArrayStream<R>
+{
+	/// Parse the next element, otherwise `None` after the end of the Array
+	fn next_element(&mut self) -> Result<Option<Json>, ParseError>
+	{
+		match self.state {
+			StreamState::Begin => {
+				let i = self.skip_whitespace()?;
+				if self.buffer[self.start + i] != b'[' {
+					return Err(self.error(ParseErrorKind::UnexpectedToken, i));
+				}
+				self.start += i + 1;
+				self.state = StreamState::Elements;
+				let i = self.skip_whitespace()?;
+				if self.buffer[self.start + i] == b']' {
+					self.start += i + 1;
+					self.finish()?;
+					return Ok(None);
+				}
+			},
+			StreamState::Elements => (),
+			StreamState::Finish => {
+				self.finish()?;
+				return Ok(None);
+			},
+			StreamState::End => return Ok(None),
+		}
+
+		// Find the comma or end of the Array after the element
+		let mut i = 0;
+		let mut depth = 0;
+		let mut in_string = false;
+		let mut escaped = false;
+		loop {
+			if self.start + i == self.buffer.len() && !self.fill()? {
+				return Err(self.error(ParseErrorKind::UnexpectedEnd, i));
+			}
+			let byte = self.buffer[self.start + i];
+			if in_string {
+				match (escaped, byte) {
+					(true, _) => escaped = false,
+					(false, b'\\') => escaped = true,
+					(false, b'"') => in_string = false,
+					_ => (),
+				}
+			}
+			else {
+				match byte {
+					b'"' => in_string = true,
+					b'[' | b'{' => depth += 1,
+					b']' | b'}' if depth > 0 => depth -= 1,
+					b',' | b']' if depth == 0 => break,
+					_ => (),
+				}
+			}
+			i += 1;
+		}
+
+		let element = &self.buffer[self.start..self.start + i];
+		if element.iter().all(|byte| matches!(byte, b'\t' | b'\n' | b'\r' | b' ')) {
+			return Err(self.error(ParseErrorKind::UnexpectedToken, i));
+		}
+		let value = match Json::parse_detailed(element) {
+			Ok(value) => value,
+			Err(error) => return Err(self.error(error.kind, error.offset)),
+		};
+		// Get the last element before checking the data after the Array, which
+		// may not have ended yet
+		if self.buffer[self.start + i] == b']' {
+			self.state = StreamState::Finish;
+		}
+		self.start += i + 1;
+		return Ok(Some(value));
+	}
+
+	/// Check that there's only whitespace after the Array
+	fn finish(&mut self) -> Result<(), ParseError>
+	{
+		return match self.skip_whitespace() {
+			Ok(i) => Err(self.error(ParseErrorKind::TrailingData, i)),
+			Err(ParseError { kind: ParseErrorKind::UnexpectedEnd, .. }) => Ok(()),
+			Err(error) => Err(error),
+		};
+	}
+
+	/// Find the index after the start of the first byte which isn't
+	/// whitespace
+	fn skip_whitespace(&mut self) -> Result<usize, ParseError>
+	{
+		let mut i = 0;
+		loop {
+			if self.start + i == self.buffer.len() && !self.fill()? {
+				return Err(self.error(ParseErrorKind::UnexpectedEnd, i));
+			}
+			match self.buffer[self.start + i] {
+				b'\t' | b'\n' | b'\r' | b' ' => i += 1,
+				_ => return Ok(i),
+			}
+		}
+	}
+
+	/// Remove the parsed bytes and read more, otherwise `false` at the end of
+	/// the data
+	fn fill(&mut self) -> Result<bool, ParseError>
+	{
+		const CHUNK_LEN: usize = 8192;
+
+		self.buffer.drain(0..self.start);
+		self.buffer_offset += self.start;
+		self.start = 0;
+
+		let len = self.buffer.len();
+		self.buffer.resize(len + CHUNK_LEN, 0);
+		let result = loop {
+			match self.reader.read(&mut self.buffer[len..len + CHUNK_LEN]) {
+				Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+				result => break result,
+			}
+		};
+		let read_len = *result.as_ref().unwrap_or(&0);
+		self.buffer.truncate(len + read_len);
+		return match result {
+			Ok(_) => Ok(read_len > 0),
+			Err(_) => Err(self.error(ParseErrorKind::Read, len)),
+		};
+	}
+
+	/// Create the error at the index after the start
+	fn error(&self, kind: ParseErrorKind, i: usize) -> ParseError
+	{
+		return ParseError { kind, offset: self.buffer_offset + self.start + i };
+	}
+}
+
+
+/// A parser which is fed chunks of data, like from a socket, and gets each
+/// complete root value as soon as it's available
+#[derive(Default)]
+pub struct StreamingParser
+{
+	buffer: Vec<u8>,
+	/// The offset in the data of the first byte in the buffer
+	buffer_offset: usize,
+	/// The index in the buffer of the first byte which isn't scanned
+	scanned: usize,
+	depth: usize,
+	in_string: bool,
+	escaped: bool,
+	in_scalar: bool,
+}
+
+
+impl StreamingParser
+{
+	/// Create a parser without any data
+	pub fn new() -> StreamingParser
+	{
+		return StreamingParser::default();
+	}
+
+	/// Buffer the chunk and get the next root value if it's complete,
+	/// otherwise `None` if more data is needed. Tokens may be split across
+	/// chunks. Feed an empty chunk to get another value which is already
+	/// buffered. After an error, the buffered data is discarded.
+	pub fn feed(&mut self, chunk: &[u8]) -> Result<Option<Json>, ParseError>
+	{
+		self.buffer.extend_from_slice(chunk);
+		return match self.find_end() {
+			Some(end) => self.parse(end).map(Some),
+			None => Ok(None),
+		};
+	}
+
+	/// Parse the rest of the data at the end of the stream, like a root
+	/// Number which didn't have anything after it, otherwise `None` if there's
+	/// only whitespace
+	pub fn finish(mut self) -> Result<Option<Json>, ParseError>
+	{
+		if self.buffer.iter().all(|byte| matches!(byte, b'\t' | b'\n' | b'\r' | b' ')) {
+			return Ok(None);
+		}
+		let end = self.buffer.len();
+		return self.parse(end).map(Some);
+	}
+
+	/// Scan the new bytes to find the end of the root value
+	fn find_end(&mut self) -> Option<usize>
+	{
+		while self.scanned < self.buffer.len() {
+			let i = self.scanned;
+			let byte = self.buffer[i];
+			self.scanned += 1;
+			if self.in_string {
+				match (self.escaped, byte) {
+					(true, _) => self.escaped = false,
+					(false, b'\\') => self.escaped = true,
+					(false, b'"') => {
+						self.in_string = false;
+						if self.depth == 0 {
+							return Some(i + 1);
+						}
+					},
+					_ => (),
+				}
+				continue;
+			}
+			// A root scalar other than a String ends at the first delimiter
+			if self.in_scalar {
+				match byte {
+					b'\t' | b'\n' | b'\r' | b' ' | b'"' | b',' | b':' | b'[' | b']' | b'{' | b'}' => return Some(i),
+					_ => continue,
+				}
+			}
+			match byte {
+				b'\t' | b'\n' | b'\r' | b' ' => (),
+				b'"' => self.in_string = true,
+				b'[' | b'{' => self.depth += 1,
+				// The end of the root container, or an invalid token
+				b']' | b'}' if self.depth <= 1 => return Some(i + 1),
+				b']' | b'}' => self.depth -= 1,
+				b',' | b':' if self.depth == 0 => return Some(i + 1),
+				_ if self.depth == 0 => self.in_scalar = true,
+				_ => (),
+			}
+		}
+		return None;
+	}
+
+	/// Parse the buffered bytes before the end and remove them
+	fn parse(&mut self, end: usize) -> Result<Json, ParseError>
+	{
+		let result = Json::parse_detailed(&self.buffer[0..end]);
+		let consumed = match result {
+			Ok(_) => end,
+			Err(_) => self.buffer.len(),
+		};
+		self.buffer.drain(0..consumed);
+		let offset = self.buffer_offset;
+		self.buffer_offset += consumed;
+		self.scanned = 0;
+		self.depth = 0;
+		self.in_string = false;
+		self.escaped = false;
+		self.in_scalar = false;
+		return result.map_err(|error| ParseError { kind: error.kind, offset: offset + error.offset });
+	}
+}
+
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	/// A reader which gets the data, then fails like a socket which is still
+	/// open without more data
+	struct OpenReader<'a>
+	{
+		data: &'a [u8],
+	}
+
+	impl Read for OpenReader<'_>
+	{
+		fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize>
+		{
+			if self.data.is_empty() {
+				return Err(io::Error::from(io::ErrorKind::WouldBlock));
+			}
+			let len = buffer.len().min(self.data.len());
+			buffer[0..len].copy_from_slice(&self.data[0..len]);
+			self.data = &self.data[len..self.data.len()];
+			return Ok(len);
+		}
+	}
+
+	#[test]
+	fn stream_array_elements()
+	{
+		let data = format!("[{}]", (0..1000).map(|i| format!("{{\"i\": [{}]}}", i)).collect::<Vec<_>>().join(", "));
+		let elements: Vec<Json> = Json::stream_array(data.as_bytes()).map(Result::unwrap).collect();
+		assert_eq!(elements.len(), 1000);
+		for (i, element) in elements.iter().enumerate() {
+			assert_eq!(element.to_string(), format!("{{\"i\":[{}]}}", i));
+		}
+		assert_eq!(Json::stream_array(&b" [ ] "[..]).count(), 0);
+	}
+
+	#[test]
+	fn stream_array_last_element_before_end_of_data()
+	{
+		let mut elements = Json::stream_array(OpenReader { data: b"[1, 2]" });
+		assert!(elements.next().is_some_and(|element| element.is_ok_and(|element| element == Json::Number(1.0))));
+		assert!(elements.next().is_some_and(|element| element.is_ok_and(|element| element == Json::Number(2.0))));
+		assert_eq!(elements.next().map(|element| element.err()), Some(Some(ParseError { kind: ParseErrorKind::Read, offset: 6 })));
+		assert!(elements.next().is_none());
+	}
+
+	#[test]
+	fn stream_array_trailing_data_after_last_element()
+	{
+		let mut elements = Json::stream_array(&b"[1] x"[..]);
+		assert!(elements.next().is_some_and(|element| element.is_ok()));
+		assert_eq!(elements.next().map(|element| element.err()), Some(Some(ParseError { kind: ParseErrorKind::TrailingData, offset: 4 })));
+		assert!(elements.next().is_none());
+	}
+
+	#[test]
+	fn stream_array_malformed_element()
+	{
+		let mut elements = Json::stream_array(&b"[1, [2 3], 4]"[..]);
+		assert!(elements.next().is_some_and(|element| element.is_ok()));
+		assert_eq!(elements.next().map(|element| element.err()), Some(Some(ParseError { kind: ParseErrorKind::UnexpectedToken, offset: 7 })));
+		assert!(elements.next().is_none());
+		assert_eq!(
+			Json::stream_array(&b"{}"[..]).next().map(|element| element.err()),
+			Some(Some(ParseError { kind: ParseErrorKind::UnexpectedToken, offset: 0 }))
+		);
+	}
+
+	/// Feed every chunk and the end of the stream to a new parser, and get
+	/// the values serialized
+	fn feed_all(chunks: &[&[u8]]) -> Result<Vec<String>, ParseError>
+	{
+		let mut parser = StreamingParser::new();
+		let mut values = Vec::<String>::new();
+		for chunk in chunks {
+			let mut chunk = *chunk;
+			while let Some(value) = parser.feed(chunk)? {
+				values.push(value.to_string());
+				chunk = b"";
+			}
+		}
+		if let Some(value) = parser.finish()? {
+			values.push(value.to_string());
+		}
+		return Ok(values);
+	}
+
+	#[test]
+	fn streaming_parser_values()
+	{
+		let data = b" {\"a\": [1, \"]}\\\"\"]} [true] \"x\\\"y\" null 12 ";
+		let expected = ["{\"a\":[1,\"]}\\\"\"]}", "[true]", "\"x\\\"y\"", "null", "12"];
+		assert_eq!(feed_all(&[data]), Ok(expected.map(String::from).to_vec()));
+		// Every token split across chunks
+		for split in 0..data.len() {
+			assert_eq!(feed_all(&[&data[0..split], &data[split..data.len()]]), Ok(expected.map(String::from).to_vec()));
+		}
+		let bytes = data.iter().map(core::slice::from_ref).collect::<Vec<_>>();
+		assert_eq!(feed_all(&bytes), Ok(expected.map(String::from).to_vec()));
+	}
+
+	#[test]
+	fn streaming_parser_waits_for_more_data()
+	{
+		let mut parser = StreamingParser::new();
+		assert_eq!(parser.feed(b"[1, {\"a\": ").ok().map(|value| value.is_none()), Some(true));
+		assert_eq!(parser.feed(b"2}").ok().map(|value| value.is_none()), Some(true));
+		assert!(parser.feed(b"]").is_ok_and(|value| value.is_some_and(|value| value.to_string() == "[1,{\"a\":2}]")));
+		assert_eq!(parser.feed(b" 1").ok().map(|value| value.is_none()), Some(true));
+		assert!(parser.finish().is_ok_and(|value| value.is_some_and(|value| value == Json::Number(1.0))));
+		assert!(StreamingParser::new().finish().is_ok_and(|value| value.is_none()));
+		let mut parser = StreamingParser::new();
+		assert!(parser.feed(b" \n").is_ok_and(|value| value.is_none()));
+		assert!(parser.finish().is_ok_and(|value| value.is_none()));
+	}
+
+	#[test]
+	fn streaming_parser_errors()
+	{
+		assert_eq!(feed_all(&[b"[1] [1 2]"]), Err(ParseError { kind: ParseErrorKind::UnexpectedToken, offset: 7 }));
+		assert_eq!(feed_all(&[b"[1", b"] ]"]), Err(ParseError { kind: ParseErrorKind::UnexpectedToken, offset: 4 }));
+		assert_eq!(feed_all(&[b"[1] tru"]), Err(ParseError { kind: ParseErrorKind::InvalidToken, offset: 4 }));
+		assert_eq!(feed_all(&[b"[1, "]), Err(ParseError { kind: ParseErrorKind::UnexpectedEnd, offset: 4 }));
+
+		// The buffered data is discarded after an error
+		let mut parser = StreamingParser::new();
+		assert!(parser.feed(b"[1 2] [3]").is_err());
+		assert!(parser.feed(b"[4]").is_ok_and(|value| value.is_some_and(|value| value.to_string() == "[4]")));
+	}
+}